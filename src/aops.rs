@@ -1,29 +1,65 @@
 use anyhow::{Context, Result};
 use askama::Template;
-use derive_builder::Builder;
-use html5ever::tree_builder::TreeSink;
-use scraper::{ElementRef, Html, Selector};
+use html5ever::{
+    driver::ParseOpts,
+    local_name, namespace_url, ns,
+    tendril::TendrilSink,
+    tokenizer::TokenizerOpts,
+    tree_builder::{TreeBuilderOpts, TreeSink},
+    QualName,
+};
+use scraper::{
+    node::{Element, Text},
+    ElementRef, Html, Node, Selector,
+};
 use serde::{Deserialize, Serialize};
-use std::ops::RangeInclusive;
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    fmt, fs, mem,
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 use strum::{Display, EnumString};
 
-#[derive(Debug, Builder, Serialize, Deserialize)]
-pub struct AopsScraper {
-    #[builder(setter(into))]
-    years: Vec<RangeInclusive<u32>>,
-    problems: RangeInclusive<u32>,
-    challenge: Challenge,
-}
+mod highlight;
+mod markdown;
+#[cfg(feature = "net")]
+mod net;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg(feature = "net")]
+pub use net::{
+    AopsScraper, AopsScraperBuilder, AopsScraperBuilderError, Auth, ProblemStatus, ScrapeProgress,
+};
+
+const AOPS_ORIGIN: &str = "https://artofproblemsolving.com";
+/// Wiki chrome removed from every parsed page before it's split up.
+const STRIPPED_SELECTORS: &[&str] = &["div#toc", ".mw-editsection", "#catlinks", ".catlinks"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AopsProblem {
     year: u32,
     number: u32,
+    #[serde(default)]
+    challenge: Challenge,
     problem: String,
-    solution: String,
+    /// One entry per solution section, each starting with its headline.
+    solutions: Vec<String>,
+    #[serde(default)]
+    choices: Vec<String>,
+    /// The letter boxed at the end of the solutions, e.g. `D` for
+    /// `\boxed{\textbf{(D)}\ 728}`.
+    #[serde(default)]
+    answer: Option<String>,
+    /// The page as downloaded, with `keep_raw_html`.
+    #[serde(skip)]
+    raw_html: Option<String>,
 }
 
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, EnumString, Display)]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, Display,
+)]
 pub enum Challenge {
     #[default]
     #[strum(serialize = "AMC_8")]
@@ -35,95 +71,434 @@ pub enum Challenge {
     #[strum(serialize = "AMC_10B")]
     #[serde(rename = "AMC_10B")]
     Amc10b,
+    #[strum(serialize = "AMC_12A")]
+    #[serde(rename = "AMC_12A")]
+    Amc12a,
+    #[strum(serialize = "AMC_12B")]
+    #[serde(rename = "AMC_12B")]
+    Amc12b,
+    /// AIME problems are numbered 1-15 and have integer answers, no choices.
+    #[strum(serialize = "AIME_I")]
+    #[serde(rename = "AIME_I")]
+    AimeI,
+    #[strum(serialize = "AIME_II")]
+    #[serde(rename = "AIME_II")]
+    AimeII,
 }
 
-#[derive(Debug, Default, Template)]
+#[derive(Debug, Default, Clone, Template)]
 #[template(path = "aops.html.j2")]
 pub struct AopsScrapeResult {
     pub styles: Vec<String>,
+    /// The contents of `styles`, rendered in one `<style>` block in place of
+    /// the `<link>`s when set.
+    pub inline_styles: Option<String>,
+    /// Stylesheets that couldn't be downloaded for `inline_styles`, and why.
+    /// The others are still inlined.
+    pub style_errors: Vec<(String, String)>,
     pub challenge: Challenge,
     pub is_solution: bool,
     pub contents: Vec<AopsContent>,
+    pub glossary: Vec<GlossaryEntry>,
+    pub report: ScrapeReport,
+    /// Problems that failed to download or parse, unless `fail_fast` is set.
+    pub errors: Vec<ScrapeError>,
+    /// Prefix every element `id` (and matching `href="#..."` links) with the
+    /// problem it came from, so anchors don't collide across problems.
+    pub namespace_ids: bool,
+    /// Height of the blank space left after each problem in the problems-only
+    /// output, as a CSS length (e.g. `"3in"`), so students have room to work.
+    pub work_space: Option<String>,
+    /// Syntax-highlight code blocks in solutions whose language is given by
+    /// a class such as `lang-python`.
+    pub highlight_code: bool,
+    pub grouping: Grouping,
+    /// CSS `font-family` for the prose, applied after the AoPS stylesheets,
+    /// e.g. `"Georgia", serif`. Math is rendered as images and unaffected.
+    pub font_family: Option<String>,
+    /// Level of the top headings (one per year, or per problem number with
+    /// `Grouping::ByNumber`), `1` by default, for embedding in a page with
+    /// its own outline. Problem titles and the headings inside problems and
+    /// solutions move down with it, stopping at `h6`.
+    pub heading_level: Option<u8>,
+    /// Rewrite wiki-relative (`/wiki/...`) and protocol-relative
+    /// (`//latex...`) `href`s and `src`s to full AoPS URLs, so links and
+    /// images work when the output is opened as a standalone file.
+    pub absolutize_links: bool,
+    /// With `MathJax` or `KaTeX`, equation images are replaced by their LaTeX
+    /// source (AoPS keeps it in the `alt` text), as `\(...\)` or `$$...$$`,
+    /// for crisp, selectable math.
+    pub math_renderer: MathRenderer,
+    /// Keep, prefix or strip the `mw-*` classes, for embedding the output in
+    /// a page with its own CSS.
+    pub wiki_classes: WikiClasses,
+    // set while `generate_combined` renders
+    combined: bool,
+    // set while `render_body_only` renders
+    body_only: bool,
+}
+
+// `AopsScrapeResult`'s template, stopping once `deadline` passes and counting
+// the problems it got to, so the result itself holds no render state
+#[derive(Template)]
+#[template(path = "aops.html.j2")]
+struct DeadlineRender<'a> {
+    result: &'a AopsScrapeResult,
+    deadline: Instant,
+    rendered: Cell<usize>,
+}
+
+/// How problems are organized in the rendered document.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Grouping {
+    /// One section per year, problems in number order.
+    #[default]
+    ByYear,
+    /// One section per problem number, across years in year order, e.g. all
+    /// the Problem 24s together.
+    ByNumber,
+}
+
+/// How the rendered document shows math.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MathRenderer {
+    /// The equation images AoPS renders, as scraped.
+    #[default]
+    Images,
+    /// LaTeX source typeset in the browser by MathJax, loaded from its CDN.
+    MathJax,
+    /// Like `MathJax`, with KaTeX.
+    KaTeX,
+}
+
+/// What happens to MediaWiki's `mw-*` classes (`mw-headline`,
+/// `mw-parser-output`, ...) in the rendered document, since the AoPS
+/// stylesheets and a host page's CSS both match them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WikiClasses {
+    #[default]
+    Keep,
+    /// Prefix them with `aops-`, e.g. `aops-mw-headline`.
+    Prefix,
+    /// Remove them.
+    Strip,
+}
+
+// A titled group of problems as rendered, see `Grouping`.
+struct Section<'a> {
+    title: String,
+    // for `namespace_ids`, like the problems' `p2003-23-`: `y2003-` for a
+    // year, `n23-` for a problem number
+    id_prefix: String,
+    problems: Vec<&'a AopsProblem>,
+}
+
+/// What happened during a scrape, beyond the scraped contents themselves.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ScrapeReport {
+    pub bytes_downloaded: u64,
+    /// (year, number) of problems not fetched because `max_bytes` was reached.
+    pub skipped: Vec<(u32, u32)>,
+    /// (year, number) of problems given up on after `problem_budget` elapsed.
+    pub abandoned: Vec<(u32, u32)>,
+    /// (year, number) of problems the wiki has no page for, e.g. problem 25
+    /// of a year that only had 23.
+    pub missing: Vec<(u32, u32)>,
+    /// `img` tags across all problems and solutions.
+    pub image_refs: usize,
+    /// Distinct image URLs among them.
+    pub unique_images: usize,
+    /// Distinct image URLs used more than once, typically common LaTeX
+    /// renders like `$\textbf{(A)}$`. Many of these make inlining cheap.
+    pub shared_images: usize,
+    /// Configured years before the contest was first held, see
+    /// `Challenge::first_year`. They are not requested at all.
+    pub years_before_contest: Vec<u32>,
+    /// Problems that failed to download in `warm_cache`. `scrape` lists
+    /// them in `AopsScrapeResult::errors` instead.
+    pub errors: Vec<ScrapeError>,
+}
+
+/// A problem left out of the result, and why.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapeError {
+    pub year: u32,
+    pub number: u32,
+    pub error: String,
+}
+
+/// The lead paragraph of a wiki page linked from one or more solutions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlossaryEntry {
+    pub title: String,
+    pub url: String,
+    pub summary: String,
+}
+
+/// Something html5ever had to repair in a problem's or solution's markup,
+/// e.g. a tag closed out of order. See `AopsScrapeResult::validate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HtmlIssue {
+    pub year: u32,
+    pub number: u32,
+    /// Whether the issue is in the solution rather than the statement.
+    pub solution: bool,
+    pub message: String,
 }
 
+/// A document whose render was cut short, e.g. because the deadline passed.
+/// `html` holds whatever was rendered before the failure.
 #[derive(Debug)]
+pub struct PartialRender {
+    pub html: String,
+    pub error: anyhow::Error,
+}
+
+impl Deref for DeadlineRender<'_> {
+    type Target = AopsScrapeResult;
+
+    fn deref(&self) -> &AopsScrapeResult {
+        self.result
+    }
+}
+
+impl DeadlineRender<'_> {
+    // called from the template before each year
+    fn within_deadline(&self) -> bool {
+        Instant::now() < self.deadline
+    }
+
+    // called from the template before each problem, counting what gets rendered
+    fn render_next(&self) -> bool {
+        let ok = self.within_deadline();
+        if ok {
+            self.rendered.set(self.rendered.get() + 1);
+        }
+        ok
+    }
+}
+
+// what `to_json` and `to_yaml` write
+#[derive(Serialize)]
+struct Export<'a> {
+    challenge: Challenge,
+    contents: &'a [AopsContent],
+}
+
+/// A contest → years → problems view of a scrape, for UIs that want to
+/// navigate the data rather than render it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContestNode {
+    pub challenge: Challenge,
+    pub years: Vec<YearNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct YearNode {
+    pub year: u32,
+    pub problems: Vec<ProblemNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProblemNode {
+    pub number: u32,
+    pub problem: String,
+    pub solutions: Vec<String>,
+    pub choices: Vec<String>,
+}
+
+/// Where `write_tree` put a year's documents, relative to the output root.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub challenge: Challenge,
+    pub year: u32,
+    pub problems: PathBuf,
+    pub solutions: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AopsContent {
     pub year: u32,
     pub problems: Vec<AopsProblem>,
 }
 
-impl AopsScraper {
-    pub async fn scrape(self) -> Result<AopsScrapeResult> {
-        let mut years = vec![];
-        for r in self.years {
-            years.extend(r);
+impl ScrapeReport {
+    fn count_images(&mut self, contents: &[AopsContent]) {
+        let img = Selector::parse("img[src]").unwrap();
+        let mut uses = BTreeMap::<String, usize>::new();
+        for problem in contents.iter().flat_map(|c| &c.problems) {
+            for html in problem.fragments() {
+                for el in Html::parse_fragment(html).select(&img) {
+                    let src = absolute_url(el.value().attr("src").unwrap_or_default());
+                    *uses.entry(src).or_default() += 1;
+                }
+            }
         }
-        Self::scrape_all(years, self.problems, self.challenge).await
+        self.image_refs = uses.values().sum();
+        self.unique_images = uses.len();
+        self.shared_images = uses.values().filter(|&&n| n > 1).count();
     }
+}
 
-    async fn scrape_all(
-        years: Vec<u32>,
-        problems: RangeInclusive<u32>,
-        challenge: Challenge,
-    ) -> Result<AopsScrapeResult> {
-        let mut contents = vec![];
-        let mut handles = vec![];
-        let mut styles = vec![];
+/// Outcome of running `parse_html` over one saved page.
+#[derive(Debug)]
+pub struct ParseCheck {
+    pub path: PathBuf,
+    pub year: u32,
+    pub number: u32,
+    pub error: Option<String>,
+}
 
-        for year in years {
-            let problems = problems.clone();
-            let handle =
-                tokio::spawn(async move { Self::scrape_problems(year, problems, challenge).await });
+/// Parse every `.html` file in `dir` without rendering, to see which page
+/// layouts the parser handles. Year and problem number are taken from file
+/// names like `2005p24.html` when present (they only label errors).
+pub fn check_dir(dir: impl AsRef<Path>) -> Result<Vec<ParseCheck>> {
+    let dir = dir.as_ref();
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.retain(|p| p.extension().is_some_and(|ext| ext == "html"));
+    paths.sort();
 
-            handles.push(handle);
-        }
+    paths
+        .into_iter()
+        .map(|path| {
+            let html = fs::read_to_string(&path)?;
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let (year, number) = match stem.split_once('p') {
+                Some((year, number)) => (year.parse().unwrap_or(0), number.parse().unwrap_or(0)),
+                None => (0, 0),
+            };
+            // file names don't say which contest; parsing doesn't differ much
+            let error = parse_html(year, number, Challenge::default(), &html, false)
+                .err()
+                .map(|e| format!("{e:#}"));
+            Ok(ParseCheck {
+                path,
+                year,
+                number,
+                error,
+            })
+        })
+        .collect()
+}
 
-        for handle in handles {
-            let (content, style_data) = handle.await??;
-            if styles.is_empty() {
-                styles = style_data;
-            }
-            contents.push(content);
+/// One line per checked page, followed by a coverage summary.
+pub fn format_checks(checks: &[ParseCheck]) -> String {
+    let mut out = String::new();
+    for check in checks {
+        match &check.error {
+            None => out.push_str(&format!("ok    {}\n", check.path.display())),
+            Some(e) => out.push_str(&format!("FAIL  {}: {e}\n", check.path.display())),
         }
+    }
+    let ok = checks.iter().filter(|c| c.error.is_none()).count();
+    out.push_str(&format!("{ok}/{} pages parsed\n", checks.len()));
+    out
+}
 
-        Ok(AopsScrapeResult {
-            styles,
-            challenge,
-            is_solution: false,
-            contents,
-        })
+impl AopsProblem {
+    pub fn challenge(&self) -> Challenge {
+        self.challenge
     }
 
-    async fn scrape_problems(
-        year: u32,
-        problems: RangeInclusive<u32>,
-        challenge: Challenge,
-    ) -> Result<(AopsContent, Vec<String>)> {
-        let mut styles = vec![];
-        let mut content = AopsContent::new(year);
-        let mut handles = vec![];
-        for problem in problems {
-            let url = get_url(year, problem, challenge);
-            let handle = tokio::spawn(async move {
-                let html = reqwest::get(&url).await?.text().await?;
-
-                let problem = parse_html(year, problem, &html)?;
-
-                Ok::<_, anyhow::Error>((html, problem))
-            });
-            handles.push(handle);
+    /// The answer choices, when they could be separated from the statement.
+    /// `None` means they're still embedded in `problem` (or there are none).
+    pub fn choices(&self) -> Option<&[String]> {
+        if self.choices.is_empty() {
+            None
+        } else {
+            Some(&self.choices)
         }
+    }
+
+    /// The page this problem was parsed from, if the scraper was set to
+    /// `keep_raw_html`, for debugging the parser.
+    pub fn raw_html(&self) -> Option<&str> {
+        self.raw_html.as_deref()
+    }
+
+    // the statement followed by each solution
+    fn fragments(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.problem).chain(&self.solutions)
+    }
+
+    /// The multiple-choice letter the solutions box, e.g. `"D"`. `None` if
+    /// there's no boxed letter or the solutions disagree.
+    pub fn answer(&self) -> Option<&str> {
+        self.answer.as_deref()
+    }
+
+    fn add_solutions(&mut self, solutions: Vec<String>) {
+        self.solutions.extend(solutions);
+        self.answer = parse_answer(&self.solutions);
+    }
 
-        for handle in handles {
-            let (html, problem) = handle.await??;
-            content.problems.push(problem);
-            content.problems.sort_by(|a, b| a.number.cmp(&b.number));
+    fn image_count(&self) -> usize {
+        let img = Selector::parse("img").unwrap();
+        self.fragments()
+            .map(|html| Html::parse_fragment(html).select(&img).count())
+            .sum()
+    }
 
-            if styles.is_empty() {
-                styles = get_stylesheets(&html)?;
+    fn to_markdown(&self) -> String {
+        let mut front = format!(
+            "---\nyear: {}\nnumber: {}\nchallenge: {}\n",
+            self.year, self.number, self.challenge
+        );
+        if !self.choices.is_empty() {
+            front.push_str("choices:\n");
+            for choice in &self.choices {
+                // JSON strings are valid YAML and take care of the escaping
+                let choice = serde_json::to_string(choice).unwrap_or_default();
+                front.push_str(&format!("  - {choice}\n"));
             }
         }
-        Ok((content, styles))
+        if let Some(answer) = self.answer() {
+            front.push_str(&format!("answer: {answer}\n"));
+        }
+        front.push_str(&format!(
+            "tags: [{}, {}]\n---\n\n",
+            self.challenge, self.year
+        ));
+
+        let mut md = format!(
+            "{front}## Problem\n\n{}\n",
+            markdown::from_html(&self.problem)
+        );
+        for solution in &self.solutions {
+            md.push_str(&format!("\n{}\n", markdown::from_html(solution)));
+        }
+        md
+    }
+}
+
+impl Challenge {
+    /// The first year the AoPS wiki has this contest's problems under this
+    /// name. The AMC 8 replaced the AJHSME in 1999; the AMC 10 and 12 began
+    /// in 2000 but were only split into A and B in 2002; the AIME has been
+    /// split into I and II since 2000.
+    pub fn first_year(self) -> u32 {
+        match self {
+            Challenge::Amc8 => 1999,
+            Challenge::Amc10a | Challenge::Amc10b | Challenge::Amc12a | Challenge::Amc12b => 2002,
+            Challenge::AimeI | Challenge::AimeII => 2000,
+        }
+    }
+
+    /// How many problems each year's contest has: 25 for the AMC, 15 for the
+    /// AIME.
+    pub fn problem_count(self) -> u32 {
+        match self {
+            Challenge::AimeI | Challenge::AimeII => 15,
+            _ => 25,
+        }
+    }
+
+    // e.g. `AMC 8`, `AIME I`
+    fn label(self) -> String {
+        self.to_string().replace('_', " ")
     }
 }
 
@@ -146,193 +521,1999 @@ impl AopsScrapeResult {
         self.is_solution = true;
         Ok(self.render()?)
     }
-}
 
-fn get_url(year: u32, problem: u32, challenge: Challenge) -> String {
-    format!(
-        "https://artofproblemsolving.com/wiki/index.php/{}_{}_Problems/Problem_{}",
-        year, challenge, problem
-    )
-}
+    /// One page with each problem followed by its solutions, folded in a
+    /// `<details>` toggle for self-study. Problems without a solution get an
+    /// empty toggle that can't be opened.
+    pub fn generate_combined(&mut self) -> Result<String> {
+        self.is_solution = false;
+        self.combined = true;
+        let html = self.render();
+        self.combined = false;
+        Ok(html?)
+    }
 
-fn get_stylesheets(html: &str) -> Result<Vec<String>> {
-    let fragment = Html::parse_document(html);
-    let styles = fragment
-        .select(&Selector::parse("link[rel=stylesheet]").unwrap())
-        .filter_map(|node| {
-            node.value().attr("href").and_then(|href| {
-                if href.ends_with("css") {
-                    Some(href.to_string())
-                } else {
-                    None
-                }
-            })
-        })
-        .collect::<Vec<_>>();
+    /// Just the rendered problems (or solutions, with `is_solution`) in their
+    /// wrapper `div`, without the doctype, `head` or `body`, for embedding in
+    /// a page of your own. That page needs the stylesheets in `styles` (or
+    /// `inline_styles`).
+    pub fn render_body_only(&mut self) -> Result<String> {
+        self.body_only = true;
+        let html = self.render();
+        self.body_only = false;
+        Ok(html?.trim().to_string())
+    }
 
-    Ok(styles)
-}
+    /// Build a result from whatever `challenge` pages a `cache_dir` holds,
+    /// without network access or a configured range. Problems are grouped by
+    /// year in number order, cached solutions subpages are merged in, and
+    /// pages that fail to parse are listed in `errors`.
+    pub fn from_cache(dir: impl AsRef<Path>, challenge: Challenge) -> Result<Self> {
+        let dir = dir.as_ref();
+        let prefix = format!("{challenge}_");
+        let mut entries = vec![];
+        for entry in
+            fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let Some(name) = name
+                .strip_suffix(".html")
+                .and_then(|n| n.strip_prefix(&prefix))
+            else {
+                continue;
+            };
+            let (name, render) = match name.strip_suffix(".render") {
+                Some(name) => (name, true),
+                None => (name, false),
+            };
+            let (name, subpage) = match name.strip_suffix("_Solutions") {
+                Some(name) => (name, true),
+                None => (name, false),
+            };
+            let Some((Ok(year), Ok(number))) = name
+                .split_once('_')
+                .map(|(year, number)| (year.parse::<u32>(), number.parse::<u32>()))
+            else {
+                continue;
+            };
+            entries.push(((year, number), subpage, render, path));
+        }
+        // full pages sort before `?action=render` ones and win
+        entries.sort();
 
-fn parse_html(year: u32, number: u32, html: &str) -> Result<AopsProblem> {
-    let fragment = Html::parse_document(html);
-    let problem = fragment
-        .select(&Selector::parse("div.mw-parser-output").unwrap())
-        .next()
-        .ok_or_else(|| anyhow::anyhow!("No problem found"))
-        .with_context(|| format!("Failed to process {year}:{number}"))?;
+        let mut pages = BTreeMap::new();
+        let mut subpages = BTreeMap::new();
+        for (key, subpage, _, path) in entries {
+            let map = if subpage { &mut subpages } else { &mut pages };
+            map.entry(key).or_insert(path);
+        }
 
-    let mut fragment = Html::parse_fragment(problem.html().as_str());
-    let node = fragment.select(&Selector::parse("div#toc").unwrap()).next();
-    let mut has_toc = false;
-    if let Some(node) = node {
-        has_toc = true;
-        fragment.remove_from_parent(&node.id());
+        let mut ret = Self {
+            challenge,
+            ..Default::default()
+        };
+        for ((year, number), path) in pages {
+            let subpage = subpages.get(&(year, number));
+            ret.add_file(year, number, &path, subpage.map(PathBuf::as_path))?;
+        }
+        ret.report.count_images(&ret.contents);
+        Ok(ret)
+    }
+
+    /// Parse saved problem pages, given as (year, number, path), with no
+    /// network access. The result is assembled like `AopsScraper::scrape`'s:
+    /// one `AopsContent` per year in year order, problems in number order,
+    /// every page's stylesheets once each, and pages that fail to parse
+    /// listed in `errors`.
+    pub fn from_files(challenge: Challenge, entries: Vec<(u32, u32, PathBuf)>) -> Result<Self> {
+        let mut ret = Self {
+            challenge,
+            ..Default::default()
+        };
+        for (year, number, path) in entries {
+            ret.add_file(year, number, &path, None)?;
+        }
+        ret.contents.sort_by_key(|c| c.year);
+        ret.report.count_images(&ret.contents);
+        Ok(ret)
     }
 
-    let problem = parse_problem(&fragment, has_toc, false, year, number)?;
-    let solution = parse_problem(&fragment, has_toc, true, year, number)?;
+    // only unreadable files are errors; unparsable ones go to `errors`
+    fn add_file(
+        &mut self,
+        year: u32,
+        number: u32,
+        path: &Path,
+        subpage: Option<&Path>,
+    ) -> Result<()> {
+        let read = |path: &Path| {
+            fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+        };
+        let html = read(path)?;
+        let mut problem = match parse_html(year, number, self.challenge, &html, false) {
+            Ok(problem) => problem,
+            Err(e) => {
+                self.errors.push(ScrapeError {
+                    year,
+                    number,
+                    error: format!("{e:#}"),
+                });
+                return Ok(());
+            }
+        };
+        if let Some(subpage) = subpage {
+            let page = read(subpage)?;
+            // the problem itself is still good
+            match parse_solutions_page(year, number, &page) {
+                Ok(solutions) => problem.add_solutions(solutions),
+                Err(e) => self.errors.push(ScrapeError {
+                    year,
+                    number,
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+        merge_styles(&mut self.styles, get_stylesheets(&html)?);
 
-    Ok(AopsProblem {
-        year,
-        number,
-        problem,
-        solution,
-    })
-}
+        match self.contents.iter_mut().find(|c| c.year == year) {
+            Some(content) => {
+                content.problems.push(problem);
+                content.problems.sort_by_key(|p| p.number);
+            }
+            None => {
+                let mut content = AopsContent::new(year);
+                content.problems.push(problem);
+                self.contents.push(content);
+            }
+        }
+        Ok(())
+    }
 
-fn parse_problem(
-    fragment: &Html,
-    has_toc: bool,
-    is_solution: bool,
-    year: u32,
-    number: u32,
-) -> Result<String> {
-    let mut fragment = fragment.clone();
-    let mut node_to_delete = vec![];
-    let mut start_to_delete = is_solution;
+    /// Re-parse every problem and solution as it would be rendered and list
+    /// the structural errors found, per problem. Empty means the output is
+    /// well-formed; renderers may disagree on how to repair anything else.
+    pub fn validate(&self) -> Vec<HtmlIssue> {
+        let mut issues = vec![];
+        for problem in self.contents.iter().flat_map(|c| &c.problems) {
+            let solutions = problem
+                .solutions
+                .iter()
+                .map(|solution| (true, self.solution_html(problem, solution)));
+            let parts = std::iter::once((false, self.problem_html(problem))).chain(solutions);
+            for (solution, html) in parts {
+                issues.extend(html_errors(&html).into_iter().map(|message| HtmlIssue {
+                    year: problem.year,
+                    number: problem.number,
+                    solution,
+                    message,
+                }));
+            }
+        }
+        issues
+    }
 
-    let problem_pos = if has_toc { 1 } else { 0 };
+    /// A plain-text overview, one line per problem, e.g.
+    /// `2023 AMC 8 #21 — answer C — 2 solutions — 3 images`. The answer is
+    /// `?` when the solutions don't box a single letter.
+    pub fn index(&self) -> String {
+        let challenge = self.challenge.label();
+        let plural = |n: usize, what: &str| {
+            if n == 1 {
+                format!("1 {what}")
+            } else {
+                format!("{n} {what}s")
+            }
+        };
+        let mut out = String::new();
+        for problem in self.contents.iter().flat_map(|c| &c.problems) {
+            out.push_str(&format!(
+                "{} {challenge} #{} — answer {} — {} — {}\n",
+                problem.year,
+                problem.number,
+                problem.answer().unwrap_or("?"),
+                plural(problem.solutions.len(), "solution"),
+                plural(problem.image_count(), "image"),
+            ));
+        }
+        out
+    }
 
-    let node = get_solution_node(
-        &fragment,
-        &["#Solution", "#Solution_1", "#Solution_1_\\(Unrigorous\\)"],
-    )
-    .ok_or_else(|| anyhow::anyhow!("No solution found"))
-    .with_context(|| format!("Failed to process {year}:{number}"))?
-    .parent()
-    .ok_or_else(|| anyhow::anyhow!("No solution parent found"))
-    .with_context(|| format!("Failed to process {year}:{number}"))?;
+    /// A copy holding only the problems from `years` numbered `numbers`, with
+    /// the same styles, challenge and render options, e.g. to write one file
+    /// per year without scraping again. An empty slice matches everything;
+    /// years left without problems are dropped. The report's problem lists
+    /// and the errors are narrowed the same way; its byte and image counts
+    /// and the glossary still describe the whole scrape.
+    pub fn filter(&self, years: &[u32], numbers: &[u32]) -> AopsScrapeResult {
+        let year_kept = |year: &u32| years.is_empty() || years.contains(year);
+        let kept = |year: &u32, number: &u32| {
+            year_kept(year) && (numbers.is_empty() || numbers.contains(number))
+        };
+        let mut ret = self.clone();
+        ret.contents.retain_mut(|content| {
+            content.problems.retain(|p| kept(&content.year, &p.number));
+            !content.problems.is_empty()
+        });
+        let report = &mut ret.report;
+        for list in [
+            &mut report.skipped,
+            &mut report.abandoned,
+            &mut report.missing,
+        ] {
+            list.retain(|(year, number)| kept(year, number));
+        }
+        report.years_before_contest.retain(year_kept);
+        report.errors.retain(|e| kept(&e.year, &e.number));
+        ret.errors.retain(|e| kept(&e.year, &e.number));
+        ret
+    }
 
-    let see_also_node = fragment
-        .select(&Selector::parse("#See_Also").unwrap())
-        .next()
-        .and_then(|node| node.parent());
+    /// One `(year, number, solution_index, problem, solution)` entry per
+    /// solution, e.g. for spaced-repetition decks that want each solution as
+    /// its own card. The problem's HTML is repeated in each of its entries;
+    /// problems without solutions have none.
+    pub fn solution_entries(&self) -> Vec<(u32, u32, usize, String, String)> {
+        self.contents
+            .iter()
+            .flat_map(|c| &c.problems)
+            .flat_map(|problem| {
+                problem.solutions.iter().enumerate().map(|(idx, solution)| {
+                    (
+                        problem.year,
+                        problem.number,
+                        idx,
+                        problem.problem.clone(),
+                        solution.clone(),
+                    )
+                })
+            })
+            .collect()
+    }
 
-    let parent = node
-        .parent()
-        .ok_or_else(|| anyhow::anyhow!("No parent found"))
-        .with_context(|| format!("Failed to process {year}:{number}"))?;
+    /// The challenge and the scraped contents, with each problem's statement
+    /// and solutions as HTML, as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.export())?)
+    }
 
-    for (idx, child) in parent.children().enumerate() {
-        if !is_solution && idx == problem_pos {
-            node_to_delete.push(child.id());
-        }
-        if child.id() == node.id() {
-            start_to_delete = !is_solution;
-        }
+    /// Like `to_json`, as YAML.
+    pub fn to_yaml(&self) -> Result<String> {
+        Ok(serde_yaml::to_string(&self.export())?)
+    }
 
-        if see_also_node.is_some() && child.id() == see_also_node.unwrap().id() {
-            start_to_delete = true;
+    fn export(&self) -> Export<'_> {
+        Export {
+            challenge: self.challenge,
+            contents: &self.contents,
         }
+    }
 
-        if start_to_delete {
-            node_to_delete.push(child.id());
+    /// The scraped data as a tree, independent of HTML rendering.
+    pub fn to_tree(&self) -> ContestNode {
+        let years = self
+            .contents
+            .iter()
+            .map(|content| YearNode {
+                year: content.year,
+                problems: content
+                    .problems
+                    .iter()
+                    .map(|p| ProblemNode {
+                        number: p.number,
+                        problem: p.problem.clone(),
+                        solutions: p.solutions.clone(),
+                        choices: p.choices.clone(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        ContestNode {
+            challenge: self.challenge,
+            years,
         }
     }
 
-    for id in node_to_delete {
-        fragment.remove_from_parent(&id);
+    /// Write one Markdown file per problem into `dir`, with YAML front matter
+    /// for static site generators. Images keep their absolute AoPS URLs.
+    pub fn write_markdown(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let mut paths = vec![];
+        for problem in self.contents.iter().flat_map(|c| &c.problems) {
+            let path = dir.join(format!(
+                "{}_{}_{}.md",
+                self.challenge, problem.year, problem.number
+            ));
+            fs::write(&path, problem.to_markdown())?;
+            paths.push(path);
+        }
+        Ok(paths)
     }
-    Ok(fragment.root_element().inner_html())
-}
 
-// ids: ["Solution", "Solution_1", "Solution_2"]
-fn get_solution_node<'a>(fragment: &'a Html, ids: &[&str]) -> Option<ElementRef<'a>> {
-    for id in ids {
-        let node = fragment.select(&Selector::parse(id).unwrap()).next();
-        if node.is_some() {
-            return node;
+    /// Every problem followed by its solutions as one Markdown document, e.g.
+    /// for importing into Anki. Each problem gets a `#` heading and the
+    /// solutions keep theirs as `##`; LaTeX comes from the equation images'
+    /// `alt` text as `$...$`/`$$...$$`.
+    pub fn to_markdown(&self) -> Result<String> {
+        let mut md = String::new();
+        for problem in self.contents.iter().flat_map(|c| &c.problems) {
+            md.push_str(&format!(
+                "# {} {} Problem {}\n\n{}\n\n",
+                problem.year,
+                self.challenge.label(),
+                problem.number,
+                markdown::from_html(&problem.problem)
+            ));
+            for solution in &problem.solutions {
+                md.push_str(&format!("{}\n\n", markdown::from_html(solution)));
+            }
         }
+        Ok(format!("{}\n", md.trim_end()))
     }
-    // fallback to use css selector. Solution should be the 2nd
-    fragment
-        .select(&Selector::parse("span.mw-headline").unwrap())
-        .nth(1)
-}
 
-#[cfg(test)]
-mod tests {
-    use std::fs;
+    /// Write one problems/solutions pair per year into `root/{challenge}/{year}/`,
+    /// creating directories as needed, plus a `manifest.json` listing them.
+    pub fn write_tree(&mut self, root: impl AsRef<Path>) -> Result<Vec<ManifestEntry>> {
+        let root = root.as_ref();
+        let mut years = mem::take(&mut self.contents).into_iter();
+        let mut done = vec![];
+        let mut manifest = vec![];
+        let mut ret = Ok(());
 
-    use super::*;
+        // render each year on its own, then put everything back
+        for content in years.by_ref() {
+            self.contents = vec![content];
+            let entry = self.write_year(root);
+            done.append(&mut self.contents);
+            match entry {
+                Ok(entry) => manifest.push(entry),
+                Err(e) => {
+                    ret = Err(e);
+                    break;
+                }
+            }
+        }
+        done.extend(years);
+        self.contents = done;
+        ret?;
 
-    #[test]
-    fn parse_html_should_work() {
-        let content = fs::read_to_string("fixtures/p23.html").unwrap();
-        let styles = get_stylesheets(&content).unwrap();
-        let result = parse_html(2003, 23, &content).unwrap();
+        fs::write(
+            root.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        Ok(manifest)
+    }
 
-        assert_eq!(result.year, 2003);
-        assert_eq!(result.number, 23);
+    fn write_year(&mut self, root: &Path) -> Result<ManifestEntry> {
+        let year = self.contents[0].year;
+        let dir = PathBuf::from(self.challenge.to_string()).join(year.to_string());
+        fs::create_dir_all(root.join(&dir))
+            .with_context(|| format!("Failed to create {}", root.join(&dir).display()))?;
 
-        insta::assert_yaml_snapshot!(styles);
+        let entry = ManifestEntry {
+            challenge: self.challenge,
+            year,
+            problems: dir.join("problems.html"),
+            solutions: dir.join("solutions.html"),
+        };
+        fs::write(root.join(&entry.problems), self.generate_problem()?)?;
+        fs::write(root.join(&entry.solutions), self.generate_solution()?)?;
+        Ok(entry)
     }
 
-    #[test]
-    fn render_problem_should_work() {
-        let content = fs::read_to_string("fixtures/p23.html").unwrap();
-        let result = parse_html(2003, 23, &content).unwrap();
-        let mut ret = AopsScrapeResult {
-            styles: get_stylesheets(&content).unwrap(),
-            contents: vec![AopsContent {
-                year: 2003,
-                problems: vec![result],
-            }],
-            ..Default::default()
-        };
+    /// Like `generate_problem`, but stops emitting problems once `timeout` has
+    /// elapsed and returns the partial document alongside the error.
+    pub fn generate_problem_within(&mut self, timeout: Duration) -> Result<String, PartialRender> {
+        self.is_solution = false;
+        self.render_within(timeout)
+    }
 
-        ret.generate_problem().unwrap();
+    /// Like `generate_solution`, but bounded by `timeout`.
+    pub fn generate_solution_within(&mut self, timeout: Duration) -> Result<String, PartialRender> {
+        self.is_solution = true;
+        self.render_within(timeout)
     }
 
-    #[test]
-    fn render_2005p24_solution_should_work() {
-        let content = fs::read_to_string("fixtures/2005p24.html").unwrap();
-        let result = parse_html(2005, 24, &content).unwrap();
-        let mut ret = AopsScrapeResult {
-            styles: get_stylesheets(&content).unwrap(),
-            contents: vec![AopsContent {
-                year: 2005,
-                problems: vec![result],
-            }],
-            ..Default::default()
+    fn render_within(&self, timeout: Duration) -> Result<String, PartialRender> {
+        let total = self
+            .contents
+            .iter()
+            .map(|c| c.problems.len())
+            .sum::<usize>();
+        let render = DeadlineRender {
+            result: self,
+            deadline: Instant::now() + timeout,
+            rendered: Cell::new(0),
         };
-
-        ret.generate_solution().unwrap();
+        let html = render.render().map_err(|e| PartialRender {
+            html: String::new(),
+            error: e.into(),
+        })?;
+        let rendered = render.rendered.get();
+        if rendered < total {
+            return Err(PartialRender {
+                html,
+                error: anyhow::anyhow!(
+                    "Render deadline of {timeout:?} exceeded after {rendered} of {total} problems"
+                ),
+            });
+        }
+        Ok(html)
+    }
+
+    fn sections(&self) -> Vec<Section<'_>> {
+        match self.grouping {
+            Grouping::ByYear => self
+                .contents
+                .iter()
+                .map(|content| Section {
+                    title: format!("{} {}", content.year, self.challenge),
+                    id_prefix: format!("y{}-", content.year),
+                    problems: content.problems.iter().collect(),
+                })
+                .collect(),
+            Grouping::ByNumber => {
+                let mut problems = self
+                    .contents
+                    .iter()
+                    .flat_map(|c| &c.problems)
+                    .collect::<Vec<_>>();
+                problems.sort_by_key(|p| (p.number, p.year));
+
+                let mut sections: Vec<Section> = vec![];
+                for problem in problems {
+                    match sections.last_mut() {
+                        Some(section) if section.problems[0].number == problem.number => {
+                            section.problems.push(problem)
+                        }
+                        _ => sections.push(Section {
+                            title: format!("{} Problem {}", self.challenge.label(), problem.number),
+                            id_prefix: format!("n{}-", problem.number),
+                            problems: vec![problem],
+                        }),
+                    }
+                }
+                sections
+            }
+        }
+    }
+
+    // e.g. `2023 AMC 8 Problem 21`, so every item says which problem it is
+    // whatever the grouping
+    fn item_title(&self, problem: &AopsProblem) -> String {
+        let kind = if self.is_solution {
+            "Solution"
+        } else {
+            "Problem"
+        };
+        format!(
+            "{} {} {kind} {}",
+            problem.year,
+            self.challenge.label(),
+            problem.number
+        )
+    }
+
+    // theming hooks on each problem's container, e.g.
+    // `aops-problem challenge-amc8 year-2023 number-21`
+    fn item_class(&self, problem: &AopsProblem) -> String {
+        let challenge = self.challenge.to_string().replace('_', "").to_lowercase();
+        format!(
+            "aops-problem challenge-{challenge} year-{} number-{}",
+            problem.year, problem.number
+        )
+    }
+
+    // problem/solution fragments as they should appear in the rendered document
+    fn problem_html(&self, problem: &AopsProblem) -> String {
+        if problem.choices.is_empty() {
+            return self.transform(problem, &problem.problem);
+        }
+        self.transform(problem, &without_choices(&problem.problem))
+    }
+
+    fn solution_html(&self, problem: &AopsProblem, solution: &str) -> String {
+        if self.highlight_code {
+            let html = highlight::highlight_code(solution);
+            return self.transform(problem, &html);
+        }
+        self.transform(problem, solution)
+    }
+
+    fn highlight_css(&self) -> String {
+        highlight::css()
+    }
+
+    // the `id` of a section's top heading, unique per section with
+    // `namespace_ids`
+    fn section_id(&self, section: &Section) -> String {
+        if self.namespace_ids {
+            format!("{}firstHeading", section.id_prefix)
+        } else {
+            "firstHeading".to_string()
+        }
+    }
+
+    // heading level `depth` below the top headings
+    fn heading(&self, depth: u8) -> u8 {
+        (self.heading_level.unwrap_or(1).clamp(1, 6) + depth).min(6)
+    }
+
+    fn transform(&self, problem: &AopsProblem, html: &str) -> String {
+        let html = match self.math_renderer {
+            MathRenderer::Images => html.to_string(),
+            MathRenderer::MathJax | MathRenderer::KaTeX => latex_as_text(html),
+        };
+        let shift = self.heading(0) - 1;
+        if !self.namespace_ids
+            && !self.absolutize_links
+            && self.wiki_classes == WikiClasses::Keep
+            && shift == 0
+        {
+            return html;
+        }
+        let prefix = format!("p{}-{}-", problem.year, problem.number);
+        rewrite_elements(&html, |el| {
+            let level = el
+                .name
+                .local
+                .strip_prefix('h')
+                .and_then(|n| n.parse::<u8>().ok());
+            if let Some(level) = level.filter(|l| (1..=6).contains(l)) {
+                el.name.local = format!("h{}", (level + shift).min(6)).as_str().into();
+            }
+            for (name, value) in el.attrs.iter_mut() {
+                let name = name.local.as_ref();
+                if self.namespace_ids && name == "id" {
+                    *value = format!("{prefix}{value}").into();
+                } else if self.namespace_ids && name == "href" && value.starts_with('#') {
+                    *value = format!("#{prefix}{}", &value[1..]).into();
+                } else if self.absolutize_links && (name == "href" || name == "src") {
+                    *value = absolute_url(value).into();
+                } else if name == "class" {
+                    *value = self.wiki_classes(value).into();
+                }
+            }
+        })
+    }
+
+    // `classes` with the `mw-*` ones handled as `wiki_classes` says; also
+    // used on the template's own headlines
+    fn wiki_classes(&self, classes: &str) -> String {
+        classes
+            .split_whitespace()
+            .filter_map(|class| match self.wiki_classes {
+                _ if !class.starts_with("mw-") => Some(class.to_string()),
+                WikiClasses::Keep => Some(class.to_string()),
+                WikiClasses::Prefix => Some(format!("aops-{class}")),
+                WikiClasses::Strip => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // the template's deadline checks; only `DeadlineRender` has a deadline
+    fn within_deadline(&self) -> bool {
+        true
+    }
+
+    fn render_next(&self) -> bool {
+        true
+    }
+}
+
+impl fmt::Display for PartialRender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for PartialRender {}
+
+// Resolve wiki-relative (`/wiki/...`) and protocol-relative (`//latex...`)
+// URLs against the AoPS origin.
+fn absolute_url(url: &str) -> String {
+    if url.starts_with("//") {
+        format!("https:{url}")
+    } else if url.starts_with('/') {
+        format!("{AOPS_ORIGIN}{url}")
+    } else {
+        url.to_string()
+    }
+}
+
+// Replace the LaTeX images in a fragment with their source, delimited for
+// MathJax and KaTeX: `$...$` becomes `\(...\)`, display math `$$...$$`.
+fn latex_as_text(html: &str) -> String {
+    let mut fragment = Html::parse_fragment(html);
+    let images = fragment
+        .select(&Selector::parse("img.latex[alt]").unwrap())
+        .filter_map(|img| Some((img.id(), img.value().attr("alt")?.trim().to_string())))
+        .collect::<Vec<_>>();
+    if images.is_empty() {
+        return html.to_string();
+    }
+    for (id, alt) in images {
+        let tex = if let Some(tex) = alt.strip_prefix("$$").and_then(|s| s.strip_suffix("$$")) {
+            format!("$${tex}$$")
+        } else if let Some(tex) = alt.strip_prefix('$').and_then(|s| s.strip_suffix('$')) {
+            format!("\\({tex}\\)")
+        } else if let Some(tex) = alt.strip_prefix("\\[").and_then(|s| s.strip_suffix("\\]")) {
+            format!("$${tex}$$")
+        } else {
+            // e.g. a bare `\begin{align*}...`
+            format!("$${alt}$$")
+        };
+        if let Some(mut node) = fragment.tree.get_mut(id) {
+            node.insert_before(Node::Text(Text { text: tex.into() }));
+            node.detach();
+        }
+    }
+    fragment.root_element().inner_html()
+}
+
+// Apply `f` to every element of an HTML fragment and serialize it back.
+fn rewrite_elements(html: &str, mut f: impl FnMut(&mut Element)) -> String {
+    let mut fragment = Html::parse_fragment(html);
+    let ids = fragment.tree.nodes().map(|n| n.id()).collect::<Vec<_>>();
+    for id in ids {
+        if let Some(mut node) = fragment.tree.get_mut(id) {
+            if let Node::Element(el) = node.value() {
+                f(el);
+            }
+        }
+    }
+    fragment.root_element().inner_html()
+}
+
+// parse errors in an HTML fragment, with html5ever's detailed messages
+fn html_errors(html: &str) -> Vec<String> {
+    let opts = ParseOpts {
+        tokenizer: TokenizerOpts {
+            exact_errors: true,
+            ..Default::default()
+        },
+        tree_builder: TreeBuilderOpts {
+            exact_errors: true,
+            ..Default::default()
+        },
+    };
+    let context = QualName::new(None, ns!(html), local_name!("body"));
+    let fragment = html5ever::parse_fragment(Html::new_fragment(), opts, context, vec![]).one(html);
+    fragment
+        .errors
+        .into_iter()
+        .map(|e| e.into_owned())
+        .collect()
+}
+
+// every stylesheet, the wiki's own `load.php?...` ones included, with
+// relative hrefs resolved so they load outside the wiki too
+fn get_stylesheets(html: &str) -> Result<Vec<String>> {
+    let fragment = Html::parse_document(html);
+    let styles = fragment
+        .select(&Selector::parse("link[rel=stylesheet]").unwrap())
+        .filter_map(|node| node.value().attr("href"))
+        .map(absolute_url)
+        .collect::<Vec<_>>();
+
+    Ok(styles)
+}
+
+// appends the stylesheets not already in `styles`, in page order
+fn merge_styles(styles: &mut Vec<String>, page: Vec<String>) {
+    for style in page {
+        if !styles.contains(&style) {
+            styles.push(style);
+        }
+    }
+}
+
+// `problems_only` skips the solution pass, so pages whose solutions can't be
+// located still yield their problem.
+fn parse_html(
+    year: u32,
+    number: u32,
+    challenge: Challenge,
+    html: &str,
+    problems_only: bool,
+) -> Result<AopsProblem> {
+    let fragment = Html::parse_document(html);
+    let problem = fragment
+        .select(&Selector::parse("div.mw-parser-output").unwrap())
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No problem found"))
+        .with_context(|| format!("Failed to process {challenge} {year}:{number}"))?;
+
+    let mut fragment = Html::parse_fragment(problem.html().as_str());
+    let has_toc = fragment
+        .select(&Selector::parse("div#toc").unwrap())
+        .next()
+        .is_some();
+    strip_cruft(&mut fragment);
+
+    let problem = parse_problem(&fragment, has_toc, false, year, number, challenge)?;
+    let solutions = if problems_only {
+        vec![]
+    } else {
+        split_solutions(&parse_problem(
+            &fragment, has_toc, true, year, number, challenge,
+        )?)
+    };
+    let choices = parse_choices(&problem).unwrap_or_default();
+    let answer = parse_answer(&solutions);
+
+    Ok(AopsProblem {
+        year,
+        number,
+        challenge,
+        problem,
+        solutions,
+        choices,
+        answer,
+        raw_html: None,
+    })
+}
+
+// Remove the wiki's navigation from a parsed page: the table of contents,
+// `[edit]` section links and the category footer.
+fn strip_cruft(fragment: &mut Html) {
+    let ids = STRIPPED_SELECTORS
+        .iter()
+        .flat_map(|s| {
+            let selector = Selector::parse(s).unwrap();
+            fragment
+                .select(&selector)
+                .map(|n| n.id())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for id in ids {
+        fragment.remove_from_parent(&id);
+    }
+}
+
+// A `..._Solutions` subpage holds nothing but solutions, so keep everything
+// up to the `See Also` footer.
+fn parse_solutions_page(year: u32, number: u32, html: &str) -> Result<Vec<String>> {
+    let mut document = Html::parse_document(html);
+    strip_cruft(&mut document);
+    let body = document
+        .select(&Selector::parse("div.mw-parser-output").unwrap())
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No solutions found"))
+        .with_context(|| format!("Failed to process {year}:{number} solutions"))?;
+
+    let see_also = Selector::parse("#See_Also").unwrap();
+    let mut solution = String::new();
+    for el in body.children().filter_map(ElementRef::wrap) {
+        if el.select(&see_also).next().is_some() {
+            break;
+        }
+        solution.push_str(&el.html());
+        solution.push('\n');
+    }
+    Ok(split_solutions(&solution))
+}
+
+// Cut a solutions section at each headline as high as its first one, so a
+// solution's own subheadings stay with it. Mere whitespace is no solution.
+fn split_solutions(html: &str) -> Vec<String> {
+    let fragment = Html::parse_fragment(html);
+    let headline = Selector::parse(".mw-headline").unwrap();
+    let mut level = None;
+    let mut solutions: Vec<String> = vec![];
+    for child in fragment.root_element().children() {
+        let (markup, heading) = match (child.value(), ElementRef::wrap(child)) {
+            (Node::Text(text), _) => (text.to_string(), None),
+            (_, Some(el)) => {
+                let name = el.value().name();
+                let is_heading = name.starts_with('h') && el.select(&headline).next().is_some();
+                (el.html(), is_heading.then_some(name))
+            }
+            _ => continue,
+        };
+        if level.is_none() {
+            level = heading;
+        }
+        match solutions.last_mut() {
+            Some(last) if heading.is_none() || heading != level => last.push_str(&markup),
+            _ if markup.trim().is_empty() => {}
+            _ => solutions.push(markup),
+        }
+    }
+    solutions
+}
+
+// the letter in the solutions' `\boxed{\textbf{(D)}...}`, if they agree
+fn parse_answer(solutions: &[String]) -> Option<String> {
+    let img = Selector::parse("img.latex[alt]").unwrap();
+    let fragments = solutions
+        .iter()
+        .map(|html| Html::parse_fragment(html))
+        .collect::<Vec<_>>();
+    let mut letters = fragments
+        .iter()
+        .flat_map(|fragment| fragment.select(&img))
+        .filter_map(|el| boxed_letter(el.value().attr("alt")?));
+    let first = letters.next()?;
+    letters.all(|l| l == first).then(|| first.to_string())
+}
+
+// `X` from `$\boxed{\textbf{(X)}\ 9}$` and similar
+fn boxed_letter(latex: &str) -> Option<char> {
+    let boxed = &latex[latex.find("\\boxed")?..];
+    let open = boxed.find('(')?;
+    let mut rest = boxed[open + 1..].chars();
+    match (rest.next()?, rest.next()?) {
+        (letter @ 'A'..='E', ')') => Some(letter),
+        _ => None,
+    }
+}
+
+// Choices can only be separated when they're the statement's last paragraph on
+// their own, e.g. `<p><img class="latex" alt="$\textbf{(A)}\ 8\qquad...$"></p>`.
+// Choices inlined with the question text, or drawn in a figure, stay put.
+fn parse_choices(problem: &str) -> Option<Vec<String>> {
+    let fragment = Html::parse_fragment(problem);
+    choices_paragraph(&fragment).map(|(_, choices)| choices)
+}
+
+// The statement without the paragraph its choices were separated from, as
+// those are rendered as a list of their own.
+fn without_choices(problem: &str) -> String {
+    let mut fragment = Html::parse_fragment(problem);
+    let Some(id) = choices_paragraph(&fragment).map(|(p, _)| p.id()) else {
+        return problem.to_string();
+    };
+    if let Some(mut node) = fragment.tree.get_mut(id) {
+        node.detach();
+    }
+    fragment.root_element().inner_html()
+}
+
+fn choices_paragraph(fragment: &Html) -> Option<(ElementRef<'_>, Vec<String>)> {
+    let img = Selector::parse("img").unwrap();
+    let has_text = |p: &ElementRef| p.text().any(|t| !t.trim().is_empty());
+    let last = fragment
+        .select(&Selector::parse("p").unwrap())
+        .rfind(|p| has_text(p) || p.select(&img).next().is_some())?;
+
+    let images = last.select(&img).collect::<Vec<_>>();
+    if has_text(&last) || images.len() != 1 {
+        return None;
+    }
+    let choices = split_choices(images[0].value().attr("alt")?)?;
+    Some((last, choices))
+}
+
+fn split_choices(latex: &str) -> Option<Vec<String>> {
+    let latex = latex.trim().trim_matches('$');
+    let mut markers: Vec<(usize, usize)> = vec![];
+    for letter in ['A', 'B', 'C', 'D', 'E'] {
+        let marker = format!("({letter})");
+        let from = markers.last().map_or(0, |&(_, end)| end);
+        let pos = latex[from..].find(&marker)? + from;
+        markers.push((pos, pos + marker.len()));
+    }
+
+    let choices = markers
+        .iter()
+        .enumerate()
+        .map(|(idx, &(_, end))| {
+            let stop = markers.get(idx + 1).map_or(latex.len(), |&(pos, _)| pos);
+            let mut text = &latex[end..stop];
+            text = text.strip_prefix('}').unwrap_or(text);
+            // drop the command wrapping the next marker, e.g. `\textbf{`
+            if let Some(pos) = text.rfind('\\') {
+                if idx + 1 < markers.len() && text.ends_with('{') {
+                    text = &text[..pos];
+                }
+            }
+            trim_latex_spaces(text).to_string()
+        })
+        .collect();
+    Some(choices)
+}
+
+fn trim_latex_spaces(mut text: &str) -> &str {
+    const SPACES: [&str; 5] = ["\\qquad", "\\quad", "\\ ", "\\,", "~"];
+    loop {
+        let trimmed = text.trim();
+        let trimmed = SPACES
+            .iter()
+            .fold(trimmed, |t, s| t.strip_prefix(s).unwrap_or(t));
+        let trimmed = SPACES
+            .iter()
+            .fold(trimmed, |t, s| t.strip_suffix(s).unwrap_or(t));
+        if trimmed == text {
+            return text;
+        }
+        text = trimmed;
+    }
+}
+
+fn parse_problem(
+    fragment: &Html,
+    has_toc: bool,
+    is_solution: bool,
+    year: u32,
+    number: u32,
+    challenge: Challenge,
+) -> Result<String> {
+    let mut fragment = fragment.clone();
+    let mut node_to_delete = vec![];
+    let mut start_to_delete = is_solution;
+
+    let problem_pos = if has_toc { 1 } else { 0 };
+
+    let node = get_solution_node(
+        &fragment,
+        &["#Solution", "#Solution_1", "#Solution_1_\\(Unrigorous\\)"],
+    );
+    // without a solution headline the problem runs up to `See Also`, but
+    // there's no telling where the solutions start
+    let node = match node {
+        Some(node) => Some(
+            node.parent()
+                .ok_or_else(|| anyhow::anyhow!("No solution parent found"))
+                .with_context(|| format!("Failed to process {challenge} {year}:{number}"))?,
+        ),
+        None if is_solution => {
+            return Err(anyhow::anyhow!("No solution found"))
+                .with_context(|| format!("Failed to process {challenge} {year}:{number}"))
+        }
+        None => None,
+    };
+
+    let see_also_node = fragment
+        .select(&Selector::parse("#See_Also").unwrap())
+        .next()
+        .and_then(|node| node.parent());
+
+    let parent = match node {
+        Some(node) => node.parent(),
+        None => fragment
+            .select(&Selector::parse("div.mw-parser-output").unwrap())
+            .next()
+            .map(|el| *el),
+    }
+    .ok_or_else(|| anyhow::anyhow!("No parent found"))
+    .with_context(|| format!("Failed to process {challenge} {year}:{number}"))?;
+
+    for (idx, child) in parent.children().enumerate() {
+        if !is_solution && idx == problem_pos {
+            node_to_delete.push(child.id());
+        }
+        if node.is_some_and(|node| child.id() == node.id()) {
+            start_to_delete = !is_solution;
+        }
+        // a video or quick section before the written solution isn't part
+        // of the problem either
+        if !is_solution && is_skipped_headline(ElementRef::wrap(child)) {
+            start_to_delete = true;
+        }
+
+        if see_also_node.is_some() && child.id() == see_also_node.unwrap().id() {
+            start_to_delete = true;
+        }
+
+        if start_to_delete {
+            node_to_delete.push(child.id());
+        }
+    }
+
+    let parent_id = parent.id();
+    for id in node_to_delete {
+        fragment.remove_from_parent(&id);
+    }
+
+    // only the container's contents; not the fragment parser's `<html>` root
+    // nor the `div.mw-parser-output` wrapper itself
+    let parent = fragment
+        .tree
+        .get(parent_id)
+        .and_then(ElementRef::wrap)
+        .ok_or_else(|| anyhow::anyhow!("No parent found"))
+        .with_context(|| format!("Failed to process {challenge} {year}:{number}"))?;
+    Ok(parent.inner_html())
+}
+
+// ids: ["Solution", "Solution_1", "Solution_2"]
+fn get_solution_node<'a>(fragment: &'a Html, ids: &[&str]) -> Option<ElementRef<'a>> {
+    for id in ids {
+        let node = fragment.select(&Selector::parse(id).unwrap()).next();
+        if node.is_some() {
+            return node;
+        }
+    }
+    let headlines = Selector::parse("span.mw-headline").unwrap();
+    // newer pages often lead with a video or a one-line answer, the written
+    // solution is the first other "Solution..." headline
+    let written = fragment.select(&headlines).find(|h| {
+        let text = h.text().collect::<String>();
+        let id = h.value().id().unwrap_or_default();
+        (id.starts_with("Solution") || text.trim_start().starts_with("Solution"))
+            && !is_video_or_quick(*h)
+    });
+    if written.is_some() {
+        return written;
+    }
+    // fallback to use css selector. Solution should be the 2nd
+    fragment.select(&headlines).nth(1)
+}
+
+fn is_skipped_headline(el: Option<ElementRef>) -> bool {
+    el.filter(|el| el.value().name() == "h2")
+        .and_then(|el| {
+            el.select(&Selector::parse("span.mw-headline").unwrap())
+                .next()
+        })
+        .is_some_and(is_video_or_quick)
+}
+
+fn is_video_or_quick(headline: ElementRef) -> bool {
+    let text = headline.text().collect::<String>().to_lowercase();
+    let id = headline.value().id().unwrap_or_default().to_lowercase();
+    ["video", "quick"]
+        .iter()
+        .any(|p| text.contains(p) || id.contains(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn parse_html_should_work() {
+        let content = fs::read_to_string("fixtures/p23.html").unwrap();
+        let styles = get_stylesheets(&content).unwrap();
+        let result = parse_html(2003, 23, Challenge::Amc8, &content, false).unwrap();
+
+        assert_eq!(result.year, 2003);
+        assert_eq!(result.number, 23);
+
+        insta::assert_yaml_snapshot!(styles);
+    }
+
+    #[test]
+    fn video_solution_first_should_be_skipped() {
+        let result = load_fixture(2003, 23, "fixtures/p23_video_first.html");
+
+        assert!(!result.problem.contains("iframe"));
+        assert!(result.solutions[0].contains("Break this problem"));
+        assert!(result.solutions.iter().all(|s| !s.contains("youtube")));
+        assert_eq!(result.answer(), Some("A"));
+    }
+
+    #[test]
+    fn edit_links_should_be_stripped() {
+        let result = load_fixture(2003, 23, "fixtures/p23_editsection.html");
+        let h2 = Selector::parse("h2").unwrap();
+        let headings = result
+            .solutions
+            .iter()
+            .flat_map(|s| {
+                Html::parse_fragment(s)
+                    .select(&h2)
+                    .map(|h| h.text().collect::<String>())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert!(!result.problem.contains("mw-editsection"));
+        assert!(result.solutions.iter().all(|s| !s.contains("catlinks")));
+        insta::assert_yaml_snapshot!(headings);
+    }
+
+    #[test]
+    fn problems_only_should_skip_broken_solutions() {
+        let html = fs::read_to_string("fixtures/2005p24_brokensolution.html").unwrap();
+        assert!(parse_html(2005, 24, Challenge::Amc8, &html, false).is_err());
+
+        let result = parse_html(2005, 24, Challenge::Amc8, &html, true).unwrap();
+        assert!(result.problem.contains("A certain calculator"));
+        assert!(result.solutions.is_empty());
+        assert_eq!(result.answer(), None);
+    }
+
+    #[test]
+    fn answers_should_be_parsed() {
+        let amc8 = Challenge::Amc8;
+        let fixtures = [
+            (2003, 23, amc8, "p23.html"),
+            (2003, 23, amc8, "p23_render.html"),
+            (2005, 24, amc8, "2005p24.html"),
+            (2005, 24, amc8, "2005p24_inline.html"),
+            (2005, 24, amc8, "2005p24_nosolution.html"),
+            (2009, 22, amc8, "2009p22.html"),
+            (2019, 1, Challenge::AimeI, "2019aimeIp1.html"),
+        ];
+        let answers: BTreeMap<_, _> = fixtures
+            .into_iter()
+            .map(|(year, number, challenge, name)| {
+                let html = fs::read_to_string(format!("fixtures/{name}")).unwrap();
+                let problem = parse_html(year, number, challenge, &html, false).unwrap();
+                (name, problem.answer().map(str::to_string))
+            })
+            .collect();
+        insta::assert_yaml_snapshot!(answers);
+    }
+
+    #[test]
+    fn parse_html_should_not_leak_wrappers() {
+        for (year, number, path) in [
+            (2003, 23, "fixtures/p23.html"),
+            (2005, 24, "fixtures/2005p24.html"),
+            (2009, 22, "fixtures/2009p22.html"),
+        ] {
+            let result = load_fixture(year, number, path);
+            for html in result.fragments() {
+                assert!(!html.contains("<html"));
+                assert!(!html.contains("<body"));
+                assert!(!html.contains("mw-parser-output"));
+            }
+            for solution in &result.solutions {
+                assert!(solution.starts_with("<h2>"));
+            }
+        }
+    }
+
+    #[test]
+    fn parse_html_should_split_solutions() {
+        let single = load_fixture(2005, 24, "fixtures/2005p24.html");
+        assert_eq!(single.solutions.len(), 1);
+
+        let result = load_fixture(2009, 22, "fixtures/2009p22.html");
+        assert_eq!(result.solutions.len(), 2);
+        assert!(result.solutions[0].contains("Super Fast"));
+        assert!(result.solutions[1].contains("Easy Casework"));
+        assert!(!result.solutions[0].contains("Easy Casework"));
+
+        // subheadings stay with their solution
+        let one = r#"<h2><span class="mw-headline" id="Solution_1">Solution 1</span></h2>"#;
+        let case = r#"<h3><span class="mw-headline" id="Case_1">Case 1</span></h3><p>a</p>"#;
+        let two = r#"<h2><span class="mw-headline" id="Solution_2">Solution 2</span></h2><p>b</p>"#;
+        let solutions = split_solutions(&format!("{one}{case}\n{two}"));
+        assert_eq!(solutions, vec![format!("{one}{case}\n"), two.to_string()]);
+    }
+
+    #[test]
+    fn render_problem_should_work() {
+        let content = fs::read_to_string("fixtures/p23.html").unwrap();
+        let result = parse_html(2003, 23, Challenge::Amc8, &content, false).unwrap();
+        let mut ret = AopsScrapeResult {
+            styles: get_stylesheets(&content).unwrap(),
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![result],
+            }],
+            ..Default::default()
+        };
+
+        ret.generate_problem().unwrap();
+    }
+
+    #[test]
+    fn render_2005p24_solution_should_work() {
+        let content = fs::read_to_string("fixtures/2005p24.html").unwrap();
+        let result = parse_html(2005, 24, Challenge::Amc8, &content, false).unwrap();
+        let mut ret = AopsScrapeResult {
+            styles: get_stylesheets(&content).unwrap(),
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![result],
+            }],
+            ..Default::default()
+        };
+
+        ret.generate_solution().unwrap();
+    }
+
+    #[test]
+    fn render_2009p22_solution_should_work() {
+        let content = fs::read_to_string("fixtures/2009p22.html").unwrap();
+        let result = parse_html(2009, 22, Challenge::Amc8, &content, false).unwrap();
+        let mut ret = AopsScrapeResult {
+            styles: get_stylesheets(&content).unwrap(),
+            contents: vec![AopsContent {
+                year: 2009,
+                problems: vec![result],
+            }],
+            ..Default::default()
+        };
+
+        ret.generate_solution().unwrap();
+    }
+
+    #[test]
+    fn parse_choices_should_work() {
+        let result = load_fixture(2005, 24, "fixtures/2005p24.html");
+        assert_eq!(result.choices().unwrap(), ["8", "9", "10", "11", "12"]);
+
+        let result = load_fixture(2009, 22, "fixtures/2009p22.html");
+        assert_eq!(
+            result.choices().unwrap(),
+            ["512", "648", "720", "728", "800"]
+        );
+
+        // choices drawn in a figure, or inlined with the question text
+        let result = load_fixture(2003, 23, "fixtures/p23.html");
+        assert!(result.choices().is_none());
+        let result = load_fixture(2005, 24, "fixtures/2005p24_inline.html");
+        assert!(result.choices().is_none());
+        assert!(result.problem.contains("textbf{(A)}"));
+    }
+
+    #[test]
+    fn choices_should_be_rendered() {
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+            }],
+            ..Default::default()
+        };
+        let html = ret.generate_problem().unwrap();
+        assert!(html.contains(r#"<ol class="choices" type="A">"#));
+        assert!(html.contains("<li>8</li>"));
+        assert!(!html.contains("textbf{(A)}"));
+
+        ret.math_renderer = MathRenderer::MathJax;
+        assert!(ret
+            .generate_combined()
+            .unwrap()
+            .contains(r"<li>\(12\)</li>"));
+
+        // inline choices stay in the statement
+        ret.contents[0].problems = vec![load_fixture(2005, 24, "fixtures/2005p24_inline.html")];
+        let html = ret.generate_problem().unwrap();
+        assert!(!html.contains(r#"class="choices""#));
+        assert!(html.contains("textbf{(A)}"));
     }
 
     #[test]
-    fn render_2009p22_solution_should_work() {
-        let content = fs::read_to_string("fixtures/2009p22.html").unwrap();
-        let result = parse_html(2009, 22, &content).unwrap();
+    fn to_tree_should_work() {
+        let ret = AopsScrapeResult {
+            challenge: Challenge::Amc8,
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+            }],
+            ..Default::default()
+        };
+
+        let tree = ret.to_tree();
+        assert_eq!(tree.years.len(), 1);
+        assert_eq!(tree.years[0].year, 2005);
+        assert_eq!(tree.years[0].problems[0].number, 24);
+        assert_eq!(tree.years[0].problems[0].choices.len(), 5);
+
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json["challenge"], "AMC_8");
+        assert_eq!(json["years"][0]["problems"][0]["number"], 24);
+    }
+
+    #[test]
+    fn check_dir_should_work() {
+        let checks = check_dir("fixtures").unwrap();
+        let find = |name: &str| {
+            checks
+                .iter()
+                .find(|c| c.path.file_name().unwrap() == name)
+                .unwrap()
+        };
+
+        assert!(find("2005p24.html").error.is_none());
+        assert_eq!(find("2005p24.html").year, 2005);
+        assert_eq!(find("2005p24.html").number, 24);
+        assert!(find("pythagorean_theorem.html")
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("No solution found"));
+
+        let table = format_checks(&checks);
+        assert!(table.contains("FAIL  fixtures/pythagorean_theorem.html"));
+        assert!(table.contains("FAIL  fixtures/2005p24_brokensolution.html"));
+        assert!(table.ends_with(&format!(
+            "{}/{} pages parsed\n",
+            checks.len() - 2,
+            checks.len()
+        )));
+    }
+
+    #[test]
+    fn namespace_ids_should_work() {
         let mut ret = AopsScrapeResult {
-            styles: get_stylesheets(&content).unwrap(),
             contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+            }],
+            ..Default::default()
+        };
+        assert!(ret
+            .generate_solution()
+            .unwrap()
+            .contains(r#"id="Solution""#));
+
+        ret.namespace_ids = true;
+        let html = ret.generate_solution().unwrap();
+        assert!(html.contains(r#"id="p2003-23-Solution""#));
+        assert!(!html.contains(r#"id="Solution""#));
+
+        ret.contents.push(AopsContent {
+            year: 2005,
+            problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+        });
+        let html = ret.generate_problem().unwrap();
+        assert!(html.contains(r#"id="y2003-firstHeading""#));
+        assert!(html.contains(r#"id="y2005-firstHeading""#));
+        assert!(!html.contains(r#"id="firstHeading""#));
+    }
+
+    #[test]
+    fn work_space_should_work() {
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+            }],
+            work_space: Some("3in".to_string()),
+            ..Default::default()
+        };
+
+        let space = r#"<div class="work-space" style="height: 3in"></div>"#;
+        assert!(ret.generate_problem().unwrap().contains(space));
+        assert!(!ret.generate_solution().unwrap().contains(space));
+    }
+
+    #[test]
+    fn write_markdown_should_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+            }],
+            ..Default::default()
+        };
+
+        let paths = ret.write_markdown(dir.path()).unwrap();
+        assert_eq!(paths, [dir.path().join("AMC_8_2005_24.md")]);
+
+        let md = fs::read_to_string(&paths[0]).unwrap();
+        assert!(md.starts_with("---\nyear: 2005\nnumber: 24\nchallenge: AMC_8\n"));
+        assert!(md.contains("  - \"11\"\n"));
+        assert!(md.contains("\nanswer: B\n"));
+        assert!(md.contains("tags: [AMC_8, 2005]\n---\n"));
+        assert!(md.contains("## Solution 1 (Unrigorous)"));
+        assert!(md.contains(r"the answer should be $\boxed{\textbf{(B)}\ 9}$"));
+        assert!(!md.contains("<img"));
+    }
+
+    #[test]
+    fn filter_should_work() {
+        let ret = AopsScrapeResult {
+            contents: vec![
+                AopsContent {
+                    year: 2005,
+                    problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+                },
+                AopsContent {
+                    year: 2009,
+                    problems: vec![load_fixture(2009, 22, "fixtures/2009p22.html")],
+                },
+            ],
+            report: ScrapeReport {
+                missing: vec![(2005, 25), (2009, 24)],
+                ..Default::default()
+            },
+            errors: vec![ScrapeError {
                 year: 2009,
+                number: 23,
+                error: "timed out".to_string(),
+            }],
+            ..Default::default()
+        };
+
+        let mut single = ret.filter(&[], &[24]);
+        assert_eq!(single.contents.len(), 1);
+        assert_eq!(single.contents[0].problems[0].number, 24);
+        let html = single.generate_problem().unwrap();
+        assert!(html.contains("2005 AMC 8 Problem 24"));
+        assert!(!html.contains("2009 AMC 8 Problem 22"));
+        assert_eq!(single.report.missing, vec![(2009, 24)]);
+        assert!(single.errors.is_empty());
+        assert_eq!(ret.filter(&[2009], &[]).errors.len(), 1);
+
+        assert!(ret.filter(&[2005], &[22]).contents.is_empty());
+        assert_eq!(ret.filter(&[], &[]).contents, ret.contents);
+    }
+
+    #[test]
+    fn solution_entries_should_work() {
+        let ret = AopsScrapeResult {
+            contents: vec![
+                AopsContent {
+                    year: 2005,
+                    problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+                },
+                AopsContent {
+                    year: 2009,
+                    problems: vec![load_fixture(2009, 22, "fixtures/2009p22.html")],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let entries = ret.solution_entries();
+        let keys = entries
+            .iter()
+            .map(|(year, number, idx, ..)| (*year, *number, *idx))
+            .collect::<Vec<_>>();
+        assert_eq!(keys, [(2005, 24, 0), (2009, 22, 0), (2009, 22, 1)]);
+        assert_eq!(entries[1].3, entries[2].3);
+        assert!(entries[1].4.contains("Super Fast"));
+        assert!(entries[2].4.contains("Easy Casework"));
+    }
+
+    #[test]
+    fn to_markdown_should_work() {
+        let ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+            }],
+            ..Default::default()
+        };
+
+        let md = ret.to_markdown().unwrap();
+        assert!(md.starts_with("# 2005 AMC 8 Problem 24\n\nA certain calculator"));
+        assert!(md.contains(r"$\textbf{(A)}\ 8\qquad"));
+        assert!(md.contains("\n## Solution 1 (Unrigorous)\n"));
+        assert!(md.contains(r"the answer should be $\boxed{\textbf{(B)}\ 9}$"));
+        assert!(!md.contains("<img"));
+    }
+
+    #[test]
+    fn highlight_code_should_work() {
+        let mut problem = load_fixture(2005, 24, "fixtures/2005p24.html");
+        problem.solutions =
+            vec![r#"<pre class="lang-python">print(sum(range(10)))</pre>"#.to_string()];
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![problem],
+            }],
+            ..Default::default()
+        };
+        assert!(!ret.generate_solution().unwrap().contains("hl-"));
+
+        ret.highlight_code = true;
+        let html = ret.generate_solution().unwrap();
+        assert!(html.contains(r#"<span class="hl-"#));
+        assert!(html.contains(".hl-"));
+
+        // blocks without a language, like the LaTeX columns on 2005p24, stay put
+        for html in load_fixture(2005, 24, "fixtures/2005p24.html").solutions {
+            assert_eq!(highlight::highlight_code(&html), html);
+        }
+    }
+
+    #[test]
+    fn solutions_subpage_should_work() {
+        // the problem page only links to its solutions
+        let result = load_fixture(2005, 24, "fixtures/2005p24_nosolution.html");
+        assert!(result.problem.contains("fewest number of keystrokes"));
+        assert!(result.solutions.is_empty());
+
+        let page = fs::read_to_string("fixtures/2005p24_solutions.html").unwrap();
+        let solutions = parse_solutions_page(2005, 24, &page).unwrap();
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions[0].contains("Solution 1"));
+        assert!(solutions[1].contains("Solution 2"));
+        assert!(!solutions[1].contains("See Also"));
+    }
+
+    #[test]
+    fn group_by_number_should_work() {
+        let mut later = load_fixture(2005, 24, "fixtures/2005p24_inline.html");
+        later.year = 2009;
+        let mut ret = AopsScrapeResult {
+            contents: vec![
+                AopsContent {
+                    year: 2005,
+                    problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+                },
+                AopsContent {
+                    year: 2009,
+                    problems: vec![load_fixture(2009, 22, "fixtures/2009p22.html"), later],
+                },
+            ],
+            grouping: Grouping::ByNumber,
+            ..Default::default()
+        };
+
+        let html = ret.generate_problem().unwrap();
+        let pos = |s: &str| html.find(s).unwrap();
+        assert!(pos(">AMC 8 Problem 22<") < pos(">AMC 8 Problem 24<"));
+        assert!(pos(">AMC 8 Problem 24<") < pos("2005 AMC 8 Problem 24"));
+        assert!(pos("2005 AMC 8 Problem 24") < pos("2009 AMC 8 Problem 24"));
+        assert!(!html.contains("2005 AMC_8"));
+    }
+
+    #[test]
+    fn lightweight_page_should_work() {
+        // the bare article markup parses exactly like the full page
+        let full = load_fixture(2003, 23, "fixtures/p23.html");
+        let bare = load_fixture(2003, 23, "fixtures/p23_render.html");
+        assert_eq!(bare.problem, full.problem);
+        assert_eq!(bare.solutions, full.solutions);
+        assert!(bare.solutions[0].contains(r#"id="Solution""#));
+
+        let html = fs::read_to_string("fixtures/p23_render.html").unwrap();
+        assert!(get_stylesheets(&html).unwrap().is_empty());
+    }
+
+    #[test]
+    fn math_renderer_should_work() {
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+            }],
+            ..Default::default()
+        };
+        let html = ret.generate_solution().unwrap();
+        assert!(html.contains(r#"class="latex""#));
+        assert!(!html.contains("MathJax"));
+
+        ret.math_renderer = MathRenderer::MathJax;
+        let html = ret.generate_solution().unwrap();
+        assert!(html.contains(r#"<script id="MathJax-script""#));
+        assert!(!html.contains(r#"class="latex""#));
+        assert!(html.contains(r"\(200\)"));
+        assert!(html.contains(r"\(\boxed{\textbf{(B)}\ 9}\)"));
+
+        ret.math_renderer = MathRenderer::KaTeX;
+        let html = ret.generate_problem().unwrap();
+        assert!(html.contains("katex.min.css"));
+        assert!(html.contains(r"<li>\(8\)</li>"));
+    }
+
+    #[test]
+    fn wiki_classes_should_work() {
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+            }],
+            ..Default::default()
+        };
+        let classes = |html: &str| {
+            Html::parse_document(html)
+                .select(&Selector::parse("[class]").unwrap())
+                .flat_map(|el| el.value().classes().map(str::to_string).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        let html = ret.generate_solution().unwrap();
+        assert!(classes(&html).iter().any(|c| c == "mw-headline"));
+
+        ret.wiki_classes = WikiClasses::Prefix;
+        let html = ret.generate_solution().unwrap();
+        let prefixed = classes(&html);
+        assert!(prefixed.iter().all(|c| !c.starts_with("mw-")));
+        assert!(prefixed.iter().any(|c| c == "aops-mw-headline"));
+        assert!(prefixed.iter().any(|c| c == "latex"));
+
+        ret.wiki_classes = WikiClasses::Strip;
+        let html = ret.generate_solution().unwrap();
+        assert!(classes(&html).iter().all(|c| !c.contains("mw-")));
+        assert!(html.contains(r#"class="latex""#));
+    }
+
+    #[test]
+    fn font_family_should_work() {
+        let mut ret = AopsScrapeResult {
+            styles: vec!["https://artofproblemsolving.com/assets/css/main-head.css".to_string()],
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+            }],
+            font_family: Some(r#""Georgia", serif"#.to_string()),
+            ..Default::default()
+        };
+        let html = ret.generate_problem().unwrap();
+        let rule = html.find(r#"font-family: "Georgia", serif;"#).unwrap();
+        assert!(html.find("main-head.css").unwrap() < rule);
+    }
+
+    #[test]
+    fn from_cache_should_work() {
+        let dir = tempfile::tempdir().unwrap();
+        for (fixture, name) in [
+            ("fixtures/2005p24_nosolution.html", "AMC_8_2005_24.html"),
+            (
+                "fixtures/2005p24_solutions.html",
+                "AMC_8_2005_24_Solutions.html",
+            ),
+            ("fixtures/p23.html", "AMC_8_2003_23.html"),
+            ("fixtures/p23_render.html", "AMC_8_2003_23.render.html"),
+            ("fixtures/2009p22.html", "AMC_10A_2009_22.html"),
+            ("fixtures/2009p22.html", "notes.html"),
+        ] {
+            fs::copy(fixture, dir.path().join(name)).unwrap();
+        }
+
+        let ret = AopsScrapeResult::from_cache(dir.path(), Challenge::Amc8).unwrap();
+        let years: Vec<_> = ret.contents.iter().map(|c| c.year).collect();
+        assert_eq!(years, vec![2003, 2005]);
+        assert_eq!(ret.contents[0].problems.len(), 1);
+        assert!(ret.contents[1].problems[0].solutions[1].contains("Solution 2"));
+        // the full page was preferred, so there are stylesheets
+        assert!(!ret.styles.is_empty());
+    }
+
+    #[test]
+    fn unparsable_subpage_should_keep_the_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::copy(
+            "fixtures/2005p24.html",
+            dir.path().join("AMC_8_2005_24.html"),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("AMC_8_2005_24_Solutions.html"),
+            "<html><body>Service unavailable</body></html>",
+        )
+        .unwrap();
+
+        let ret = AopsScrapeResult::from_cache(dir.path(), Challenge::Amc8).unwrap();
+        assert_eq!(ret.contents[0].problems[0].number, 24);
+        assert_eq!(ret.contents[0].problems[0].solutions.len(), 1);
+        assert_eq!((ret.errors[0].year, ret.errors[0].number), (2005, 24));
+        assert!(ret.errors[0].error.contains("No solutions found"));
+    }
+
+    #[test]
+    fn problem_classes_should_work() {
+        let mut ret = AopsScrapeResult {
+            challenge: Challenge::Amc10a,
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+            }],
+            ..Default::default()
+        };
+        let html = ret.generate_solution().unwrap();
+        assert!(html.contains(
+            r#"<div class="aops-problem challenge-amc10a year-2003 number-23" data-challenge="AMC_10A" data-year="2003""#
+        ));
+        assert!(html.contains(r#"data-number="23">"#));
+    }
+
+    #[test]
+    fn validate_should_work() {
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+            }],
+            ..Default::default()
+        };
+        assert!(ret.validate().is_empty());
+
+        ret.contents[0].problems[0].solutions = vec!["<p><b>Answer</p></b>".to_string()];
+        let issues = ret.validate();
+        assert!(!issues.is_empty());
+        assert!(issues
+            .iter()
+            .all(|i| i.year == 2003 && i.number == 23 && i.solution));
+    }
+
+    #[test]
+    fn count_images_should_work() {
+        let mut problem = load_fixture(2003, 23, "fixtures/p23.html");
+        problem.problem =
+            r#"<img src="//latex.artofproblemsolving.com/a.png"> <img src="/b.png">"#.into();
+        problem.solutions = vec![
+            r#"<img src="https://latex.artofproblemsolving.com/a.png"><img src="/c.png">"#.into(),
+        ];
+        let contents = vec![AopsContent {
+            year: 2003,
+            problems: vec![problem],
+        }];
+
+        let mut report = ScrapeReport::default();
+        report.count_images(&contents);
+        assert_eq!(report.image_refs, 4);
+        assert_eq!(report.unique_images, 3);
+        assert_eq!(report.shared_images, 1);
+    }
+
+    #[test]
+    fn absolutize_links_should_work() {
+        let mut problem = load_fixture(2003, 23, "fixtures/p23.html");
+        problem.solutions = vec![r##"<p><a href="/wiki/index.php/Triangle">triangle</a>
+            <img src="//latex.artofproblemsolving.com/a/b.png" alt="$x$">
+            <a href="#fn1">1</a> <a href="https://example.com/x">x</a></p>"##
+            .into()];
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![problem],
+            }],
+            ..Default::default()
+        };
+        let html = ret.generate_solution().unwrap();
+        assert!(html.contains(r#"href="/wiki/index.php/Triangle""#));
+
+        ret.absolutize_links = true;
+        let html = ret.generate_solution().unwrap();
+        assert!(html.contains(r#"href="https://artofproblemsolving.com/wiki/index.php/Triangle""#));
+        assert!(html.contains(r#"src="https://latex.artofproblemsolving.com/a/b.png""#));
+        assert!(html.contains(r##"href="#fn1""##));
+        assert!(html.contains(r#"href="https://example.com/x""#));
+    }
+
+    #[test]
+    fn heading_level_should_work() {
+        let mut problem = load_fixture(2003, 23, "fixtures/p23.html");
+        problem.solutions = vec!["<h2>Solution 1</h2><h5>Note</h5><p>42</p>".into()];
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![problem],
+            }],
+            heading_level: Some(3),
+            ..Default::default()
+        };
+        let html = ret.generate_solution().unwrap();
+        assert!(html.contains(r#"<h3 id="firstHeading" class="firstHeading">2003 AMC_8</h3>"#));
+        assert!(
+            html.contains(r#"<h4><span class="mw-headline">2003 AMC 8 Solution 23</span></h4>"#)
+        );
+        assert!(html.contains("<h4>Solution 1</h4><h6>Note</h6>"));
+
+        let html = ret.generate_problem().unwrap();
+        assert!(html.contains(r#"<h4><span class="mw-headline">2003 AMC 8 Problem 23</span></h4>"#));
+
+        // out-of-range levels from a config stop at `h6`
+        ret.heading_level = Some(u8::MAX);
+        let html = ret.generate_problem().unwrap();
+        assert!(html.contains(r#"<h6 id="firstHeading""#));
+    }
+
+    #[test]
+    fn index_should_work() {
+        let ret = AopsScrapeResult {
+            contents: vec![
+                AopsContent {
+                    year: 2003,
+                    problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+                },
+                AopsContent {
+                    year: 2009,
+                    problems: vec![load_fixture(2009, 22, "fixtures/2009p22.html")],
+                },
+            ],
+            ..Default::default()
+        };
+        let index = ret.index();
+        let lines: Vec<_> = index.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("2003 AMC 8 #23 — answer A — 3 solutions — "));
+        assert!(lines[1].starts_with("2009 AMC 8 #22 — answer D — 2 solutions — "));
+        assert!(lines[1].ends_with(" images"));
+
+        assert_eq!(boxed_letter(r"$\boxed{\textbf{(B)}\ 9}$"), Some('B'));
+        assert_eq!(boxed_letter(r"$\boxed{\textbf{Javapost}}$"), None);
+    }
+
+    #[test]
+    fn aime_should_work() {
+        assert_eq!(
+            "AIME_II".parse::<Challenge>().unwrap().to_string(),
+            "AIME_II"
+        );
+
+        let html = fs::read_to_string("fixtures/2019aimeIp1.html").unwrap();
+        let result = parse_html(2019, 1, Challenge::AimeI, &html, false).unwrap();
+        assert_eq!(result.challenge(), Challenge::AimeI);
+        assert!(result.problem.contains("sum of the digits"));
+        assert!(!result.problem.contains("Solution"));
+        assert_eq!(result.solutions.len(), 2);
+        assert!(result.solutions[0].contains("Solution 1"));
+        assert!(result.solutions[1].contains("Solution 2"));
+        assert!(!result.solutions[1].contains("See Also"));
+        // integer answers, nothing to split off
+        assert!(result.choices().is_none());
+    }
+
+    #[test]
+    fn stylesheets_should_be_merged_across_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let other = fs::read_to_string("fixtures/2005p24.html")
+            .unwrap()
+            .replace("assets/css/footer.css", "assets/css/print.css");
+        fs::write(dir.path().join("2005p24.html"), other).unwrap();
+
+        let ret = AopsScrapeResult::from_files(
+            Challenge::Amc8,
+            vec![
+                (2003, 23, "fixtures/p23.html".into()),
+                (2005, 24, dir.path().join("2005p24.html")),
+            ],
+        )
+        .unwrap();
+        let mut expected =
+            get_stylesheets(&fs::read_to_string("fixtures/p23.html").unwrap()).unwrap();
+        expected.push("https://artofproblemsolving.com/assets/css/print.css".to_string());
+        assert_eq!(ret.styles, expected);
+    }
+
+    #[test]
+    fn from_files_should_work() {
+        let ret = AopsScrapeResult::from_files(
+            Challenge::Amc8,
+            vec![
+                (2005, 24, "fixtures/2005p24.html".into()),
+                (2003, 23, "fixtures/p23.html".into()),
+                (2005, 23, "fixtures/2005p24_inline.html".into()),
+                (2005, 25, "fixtures/pythagorean_theorem.html".into()),
+            ],
+        )
+        .unwrap();
+
+        let years: Vec<_> = ret.contents.iter().map(|c| c.year).collect();
+        assert_eq!(years, vec![2003, 2005]);
+        let numbers: Vec<_> = ret.contents[1].problems.iter().map(|p| p.number).collect();
+        assert_eq!(numbers, vec![23, 24]);
+        assert_eq!(
+            ret.styles,
+            get_stylesheets(&fs::read_to_string("fixtures/2005p24.html").unwrap()).unwrap()
+        );
+        assert_eq!((ret.errors[0].year, ret.errors[0].number), (2005, 25));
+
+        let missing = vec![(2005, 1, "fixtures/nope.html".into())];
+        assert!(AopsScrapeResult::from_files(Challenge::Amc8, missing).is_err());
+    }
+
+    #[test]
+    fn render_body_only_should_work() {
+        let mut ret = AopsScrapeResult {
+            styles: vec!["https://artofproblemsolving.com/assets/css/main-head.css".to_string()],
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+            }],
+            ..Default::default()
+        };
+        let body = ret.render_body_only().unwrap();
+        assert!(body.starts_with(r#"<div class="page-wrapper">"#));
+        assert!(body.ends_with("</div>"));
+        assert!(body.contains("2003 AMC 8 Problem 23"));
+        assert!(!body.contains("<head>"));
+        assert!(!body.contains("main-head.css"));
+
+        ret.is_solution = true;
+        assert!(ret
+            .render_body_only()
+            .unwrap()
+            .contains("2003 AMC 8 Solution 23"));
+
+        let page = ret.generate_problem().unwrap();
+        assert!(page.trim_start().starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("main-head.css"));
+    }
+
+    #[test]
+    fn generate_combined_should_work() {
+        let mut unsolved = load_fixture(2005, 24, "fixtures/2005p24_nosolution.html");
+        unsolved.number = 25;
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html"), unsolved],
+            }],
+            ..Default::default()
+        };
+        let html = ret.generate_combined().unwrap();
+        let problem = html.find("Problem 24").unwrap();
+        let toggle = html.find("<summary>Solution</summary>").unwrap();
+        assert!(problem < toggle);
+        assert!(html[toggle..].contains("Solution 1"));
+        assert!(html.contains(r#"<details class="solutions empty">"#));
+        assert!(!ret.combined);
+
+        // the separate renders are unaffected
+        assert!(!ret.generate_problem().unwrap().contains("<details"));
+    }
+
+    #[test]
+    fn to_json_and_yaml_should_round_trip() {
+        let ret = AopsScrapeResult {
+            challenge: Challenge::Amc8,
+            contents: vec![AopsContent {
+                year: 2005,
+                problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+            }],
+            ..Default::default()
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&ret.to_json().unwrap()).unwrap();
+        assert_eq!(json["challenge"], "AMC_8");
+        assert_eq!(json["contents"][0]["problems"][0]["number"], 24);
+        let contents: Vec<AopsContent> = serde_json::from_value(json["contents"].clone()).unwrap();
+        assert_eq!(contents, ret.contents);
+
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&ret.to_yaml().unwrap()).unwrap();
+        let contents: Vec<AopsContent> = serde_yaml::from_value(yaml["contents"].clone()).unwrap();
+        assert_eq!(contents, ret.contents);
+    }
+
+    #[test]
+    fn write_tree_should_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ret = AopsScrapeResult {
+            contents: vec![
+                AopsContent {
+                    year: 2005,
+                    problems: vec![load_fixture(2005, 24, "fixtures/2005p24.html")],
+                },
+                AopsContent {
+                    year: 2009,
+                    problems: vec![load_fixture(2009, 22, "fixtures/2009p22.html")],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let manifest = ret.write_tree(dir.path()).unwrap();
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(ret.contents.len(), 2);
+        assert!(dir.path().join("AMC_8/2005/problems.html").exists());
+        assert!(dir.path().join("AMC_8/2009/solutions.html").exists());
+
+        let saved = fs::read_to_string(dir.path().join("manifest.json")).unwrap();
+        let saved: Vec<ManifestEntry> = serde_json::from_str(&saved).unwrap();
+        assert_eq!(saved[1].problems, PathBuf::from("AMC_8/2009/problems.html"));
+    }
+
+    #[test]
+    fn render_within_deadline_should_work() {
+        let content = fs::read_to_string("fixtures/p23.html").unwrap();
+        let result = parse_html(2003, 23, Challenge::Amc8, &content, false).unwrap();
+        let mut ret = AopsScrapeResult {
+            contents: vec![AopsContent {
+                year: 2003,
                 problems: vec![result],
             }],
             ..Default::default()
         };
 
-        ret.generate_solution().unwrap();
+        let html = ret
+            .generate_problem_within(Duration::from_secs(60))
+            .unwrap();
+        assert!(html.contains("Problem 23"));
+
+        let partial = ret.generate_solution_within(Duration::ZERO).unwrap_err();
+        assert!(!partial.html.contains("Solution 23"));
+        assert!(partial.error.to_string().contains("0 of 1 problems"));
+
+        // results can be shared across threads, e.g. in an `Arc`
+        fn assert_sync<T: Send + Sync>() {}
+        assert_sync::<AopsScrapeResult>();
+    }
+
+    pub(super) fn load_fixture(year: u32, number: u32, path: &str) -> AopsProblem {
+        let content = fs::read_to_string(path).unwrap();
+        parse_html(year, number, Challenge::Amc8, &content, false).unwrap()
     }
 }
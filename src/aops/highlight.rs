@@ -0,0 +1,91 @@
+use scraper::{node::Text, ElementRef, Html, Node, Selector};
+use std::sync::OnceLock;
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+const THEME: &str = "InspiredGitHub";
+
+/// Highlight `pre` blocks whose language is known from a `lang-*`,
+/// `language-*` or `mw-highlight-lang-*` class on the block, its parent or
+/// its `code` child. Blocks without a recognized language are left as is.
+pub(super) fn highlight_code(html: &str) -> String {
+    let mut fragment = Html::parse_fragment(html);
+    let mut blocks = vec![];
+    for el in fragment.select(&Selector::parse("pre").unwrap()) {
+        let Some(syntax) = language(el).and_then(|l| syntaxes().find_syntax_by_token(&l)) else {
+            continue;
+        };
+        let code = el.text().collect::<String>();
+        let mut generator =
+            ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes(), CLASS_STYLE);
+        let ok = LinesWithEndings::from(&code).all(|line| {
+            generator
+                .parse_html_for_line_which_includes_newline(line)
+                .is_ok()
+        });
+        if ok {
+            blocks.push((el.id(), generator.finalize()));
+        }
+    }
+    if blocks.is_empty() {
+        return html.to_string();
+    }
+
+    // swap each block's contents for a placeholder, then splice the
+    // highlighted markup in after serializing
+    for (idx, (id, _)) in blocks.iter().enumerate() {
+        let Some(mut node) = fragment.tree.get_mut(*id) else {
+            continue;
+        };
+        while let Some(mut child) = node.first_child() {
+            child.detach();
+        }
+        node.append(Node::Text(Text {
+            text: placeholder(idx).into(),
+        }));
+    }
+    let mut out = fragment.root_element().inner_html();
+    for (idx, (_, code)) in blocks.iter().enumerate() {
+        out = out.replace(&placeholder(idx), code);
+    }
+    out
+}
+
+/// Stylesheet for the classes emitted by `highlight_code`.
+pub(super) fn css() -> String {
+    let themes = ThemeSet::load_defaults();
+    css_for_theme_with_class_style(&themes.themes[THEME], CLASS_STYLE).unwrap_or_default()
+}
+
+fn language(pre: ElementRef) -> Option<String> {
+    let code = pre
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find(|c| c.value().name() == "code");
+    let parent = pre.parent().and_then(ElementRef::wrap);
+
+    [Some(pre), code, parent]
+        .into_iter()
+        .flatten()
+        .flat_map(|el| el.value().classes())
+        .find_map(|class| {
+            ["mw-highlight-lang-", "language-", "lang-"]
+                .iter()
+                .find_map(|prefix| class.strip_prefix(prefix))
+        })
+        .map(str::to_string)
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn placeholder(idx: usize) -> String {
+    format!("@@aops-highlight-{idx}@@")
+}
@@ -0,0 +1,147 @@
+use super::absolute_url;
+use scraper::{ElementRef, Html, Node, Selector};
+
+/// Convert a parsed problem/solution fragment to Markdown. LaTeX images are
+/// replaced by their source (AoPS keeps it in the `alt` text), other images
+/// and links point at absolute URLs so the output works outside the wiki.
+pub(super) fn from_html(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+    write_children(fragment.root_element(), &mut out);
+    tidy(&out)
+}
+
+fn write_children(el: ElementRef, out: &mut String) {
+    for child in el.children() {
+        match child.value() {
+            Node::Text(text) => {
+                // collapse whitespace runs, including across adjacent nodes
+                let mut last = if out.ends_with(char::is_whitespace) {
+                    ' '
+                } else {
+                    '.'
+                };
+                for c in text.chars() {
+                    let c = if c.is_whitespace() { ' ' } else { c };
+                    if c != ' ' || last != ' ' {
+                        out.push(c);
+                    }
+                    last = c;
+                }
+            }
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_element(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_element(el: ElementRef, out: &mut String) {
+    let v = el.value();
+    match v.name() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = v.name()[1..].parse::<usize>().unwrap_or(2);
+            let text = el.text().collect::<String>();
+            out.push_str(&format!("\n\n{} {}\n\n", "#".repeat(level), text.trim()));
+        }
+        "p" | "div" | "center" | "table" | "tr" => {
+            out.push_str("\n\n");
+            write_children(el, out);
+            out.push_str("\n\n");
+        }
+        "pre" => {
+            out.push_str("\n\n");
+            write_pre(el, out);
+            out.push_str("\n\n");
+        }
+        "br" => out.push('\n'),
+        "b" | "strong" => out.push_str(&format!("**{}**", inline(el).trim())),
+        "i" | "em" => out.push_str(&format!("*{}*", inline(el).trim())),
+        "ul" | "ol" => {
+            out.push_str("\n\n");
+            let ordered = v.name() == "ol";
+            let items = el
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|c| c.value().name() == "li");
+            for (idx, item) in items.enumerate() {
+                let marker = if ordered {
+                    format!("{}.", idx + 1)
+                } else {
+                    "-".to_string()
+                };
+                out.push_str(&format!("{marker} {}\n", inline(item).trim()));
+            }
+            out.push('\n');
+        }
+        "img" => match v.attr("alt") {
+            Some(alt) if v.classes().any(|c| c == "latex") => out.push_str(&latex(alt)),
+            alt => {
+                let src = absolute_url(v.attr("src").unwrap_or_default());
+                out.push_str(&format!("![{}]({src})", alt.unwrap_or_default()));
+            }
+        },
+        "a" if el.select(&Selector::parse("img").unwrap()).next().is_some() => {
+            // image links (to the wiki's File: pages) keep just the image
+            write_children(el, out);
+        }
+        "a" => match v.attr("href") {
+            Some(href) => {
+                let text = inline(el);
+                out.push_str(&format!("[{}]({})", text.trim(), absolute_url(href)));
+            }
+            None => write_children(el, out),
+        },
+        "script" | "style" => {}
+        _ => write_children(el, out),
+    }
+}
+
+// like `write_children`, but keeps line breaks. On AoPS `pre` mostly wraps a
+// column of LaTeX images, one per line.
+fn write_pre(el: ElementRef, out: &mut String) {
+    for child in el.children() {
+        match (child.value(), ElementRef::wrap(child)) {
+            (Node::Text(text), _) => out.push_str(text),
+            (_, Some(child)) => write_element(child, out),
+            _ => {}
+        }
+    }
+}
+
+fn inline(el: ElementRef) -> String {
+    let mut out = String::new();
+    write_children(el, &mut out);
+    out
+}
+
+// `$...$` is already Markdown math; display math like `\[...\]` becomes `$$...$$`
+fn latex(alt: &str) -> String {
+    let alt = alt.trim();
+    match alt.strip_prefix("\\[").and_then(|s| s.strip_suffix("\\]")) {
+        Some(display) => format!("$${}$$", display.trim()),
+        None => alt.to_string(),
+    }
+}
+
+// trim every line and collapse the blank lines left by nested blocks
+fn tidy(md: &str) -> String {
+    let mut out = String::new();
+    let mut blank = true;
+    for line in md.lines().map(str::trim) {
+        if line.is_empty() {
+            if !blank {
+                out.push('\n');
+            }
+            blank = true;
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+        blank = false;
+    }
+    out.trim_end().to_string()
+}
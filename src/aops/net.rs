@@ -0,0 +1,2089 @@
+use super::{
+    absolute_url, get_stylesheets, merge_styles, parse_html, parse_solutions_page,
+    rewrite_elements, AopsContent, AopsProblem, AopsScrapeResult, Challenge, GlossaryEntry,
+    ScrapeError, ScrapeReport, AOPS_ORIGIN,
+};
+use anyhow::{Context, Result};
+use derive_builder::Builder;
+use futures::{stream::FuturesUnordered, Stream, StreamExt};
+use reqwest::cookie::Jar;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    future::Future,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::{mpsc, Semaphore};
+
+tokio::task_local! {
+    // set while a task holds a permit for all of a problem's requests
+    static HOLDS_PERMIT: ();
+}
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+/// The longest wait between two retries, however large the backoff grows.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Builder, Serialize, Deserialize)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct AopsScraper {
+    /// Years to scrape; see also `year_list`. Repeated years are fetched
+    /// once.
+    #[builder(setter(into))]
+    #[serde(deserialize_with = "numbers")]
+    years: Vec<RangeInclusive<u32>>,
+    /// Problem numbers to scrape in each year; see also `problem_list`.
+    /// Repeated numbers are fetched once.
+    #[builder(setter(custom))]
+    #[serde(deserialize_with = "numbers")]
+    problems: Vec<RangeInclusive<u32>>,
+    challenge: Challenge,
+    /// Follow concept links (theorems, definitions) in the solutions and
+    /// append their lead paragraphs as a glossary.
+    #[builder(default)]
+    #[serde(default)]
+    glossary: bool,
+    /// Fetch years and problems one at a time, in order, so style selection
+    /// and output are reproducible run to run at the cost of parallelism.
+    #[builder(default)]
+    #[serde(default)]
+    deterministic: bool,
+    /// Stop fetching once this many bytes have been downloaded in total.
+    /// Pages not fetched are listed in `ScrapeReport::skipped`.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    max_bytes: Option<u64>,
+    /// Also fetch `.../Problem_N_Solutions` pages and append their solutions,
+    /// for contests that keep solutions on a separate subpage.
+    #[builder(default)]
+    #[serde(default)]
+    solution_subpages: bool,
+    /// Fetch MediaWiki's `?action=render` variant of each page: just the
+    /// article markup, without the skin or stylesheets. Much smaller, and
+    /// enough for text, JSON or Markdown output.
+    #[builder(default)]
+    #[serde(default)]
+    lightweight: bool,
+    /// Only parse the problems, for worksheets: solutions are left empty,
+    /// and pages whose solutions can't be located still yield a problem.
+    #[builder(default)]
+    #[serde(default)]
+    problems_only: bool,
+    /// Keep each downloaded page on its problem, see `AopsProblem::raw_html`.
+    #[builder(default)]
+    #[serde(default)]
+    keep_raw_html: bool,
+    /// Total wall-clock time allowed for fetching one problem, counted from
+    /// when its turn comes under `max_concurrency`. Problems still pending
+    /// after that are abandoned and listed in `ScrapeReport::abandoned`
+    /// instead of holding up the rest of the scrape.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    problem_budget: Option<Duration>,
+    /// Give up on a single request attempt after this long. A timed out
+    /// attempt is retried like any other transient failure, see
+    /// `max_retries`; once those run out the problem fails with an error,
+    /// listed in `AopsScrapeResult::errors`. Defaults to 30 seconds; zero
+    /// disables it.
+    #[builder(default = "Duration::from_secs(30)")]
+    #[serde(default = "default_request_timeout")]
+    request_timeout: Duration,
+    /// Keep downloaded pages in this directory, as
+    /// `{challenge}_{year}_{number}.html`, and read them from there instead
+    /// of the network on later runs.
+    #[builder(default, setter(into, strip_option))]
+    #[serde(default)]
+    cache_dir: Option<PathBuf>,
+    /// Sign in to AoPS for member-only pages. Authentication happens once,
+    /// before the first fetch, and the session cookie is sent with every
+    /// request after; it costs one extra request and doesn't change how many
+    /// pages are fetched at a time. Without it requests are anonymous.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    auth: Option<Auth>,
+    /// How many times to retry a request that failed to connect, timed out,
+    /// or got a 5xx or 429 response. Other errors, like the 404 of a problem
+    /// that doesn't exist, are never retried.
+    #[builder(default = "3")]
+    #[serde(default = "default_max_retries")]
+    max_retries: u32,
+    /// Wait before the first retry, doubled for each one after, up to a
+    /// minute.
+    #[builder(default = "Duration::from_millis(500)")]
+    #[serde(default = "default_retry_backoff")]
+    retry_backoff: Duration,
+    /// Most requests in flight at once, across all years and problems, so a
+    /// large scrape doesn't get rate-limited. `0` lifts the limit.
+    #[builder(default = "8")]
+    #[serde(default = "default_max_concurrency")]
+    max_concurrency: usize,
+    /// Abort the whole scrape on the first problem that fails to download or
+    /// parse. By default such problems are listed in
+    /// `AopsScrapeResult::errors` and the rest are kept.
+    #[builder(default)]
+    #[serde(default)]
+    fail_fast: bool,
+    /// Download the pages' stylesheets and embed them in the output, so it
+    /// renders the same offline. See `AopsScrapeResult::inline_styles`.
+    #[builder(default)]
+    #[serde(default)]
+    inline_styles: bool,
+    /// Download every image in the problems and solutions into this
+    /// directory and point their `src` at the copy, so the output works
+    /// offline. The new `src` is the path under this directory as given, so
+    /// keep it relative to where the HTML will be written. Images that fail
+    /// to download keep their remote `src`.
+    #[builder(default, setter(into, strip_option))]
+    #[serde(default)]
+    assets_dir: Option<PathBuf>,
+    /// Send every request through this client, e.g. to set a proxy, timeouts
+    /// or headers. By default the scraper builds its own, with a User-Agent
+    /// naming this crate. Logging in with `auth` needs the client to keep
+    /// cookies (`cookie_store(true)`); `Auth::Cookie` needs the default
+    /// client.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip)]
+    client: Option<reqwest::Client>,
+    /// Origin of the wiki, for a mirror or a local copy: problem pages are
+    /// fetched from `{base_url}/wiki/index.php/...`. A trailing `/` makes no
+    /// difference. Stylesheets and images still come from wherever the pages
+    /// point.
+    #[builder(default = "AOPS_ORIGIN.to_string()", setter(into))]
+    #[serde(default = "default_base_url")]
+    base_url: String,
+    /// Send a `ScrapeProgress` here as each problem finishes, e.g. to show
+    /// progress while a long scrape runs.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip)]
+    progress: Option<mpsc::UnboundedSender<ScrapeProgress>>,
+}
+
+impl AopsScraperBuilder {
+    /// Scrape this range of problem numbers in each year.
+    pub fn problems(&mut self, problems: RangeInclusive<u32>) -> &mut Self {
+        self.problems = Some(vec![problems]);
+        self
+    }
+
+    /// Scrape exactly these problem numbers, e.g. `&[1, 5, 25]`.
+    pub fn problem_list(&mut self, numbers: &[u32]) -> &mut Self {
+        self.problems = Some(numbers.iter().map(|&n| n..=n).collect());
+        self
+    }
+
+    /// Scrape exactly these years, e.g. `&[2019, 2021, 2023]`.
+    pub fn year_list(&mut self, years: &[u32]) -> &mut Self {
+        self.years(years.iter().map(|&y| y..=y).collect::<Vec<_>>())
+    }
+
+    /// Fetch and parse just problem `number` of `challenge`'s `year`, without
+    /// setting years, problems or a challenge first. The rest of the
+    /// configuration (base URL, cache, auth, retries, budgets and timeouts)
+    /// applies as in `AopsScraper::scrape`.
+    pub async fn scrape_one(
+        &self,
+        year: u32,
+        number: u32,
+        challenge: Challenge,
+    ) -> Result<AopsProblem> {
+        let count = challenge.problem_count();
+        anyhow::ensure!(
+            (1..=count).contains(&number),
+            "{} has problems 1..={count}, got {number}",
+            challenge.label()
+        );
+        let scraper = self
+            .clone()
+            .challenge(challenge)
+            .year_list(&[year])
+            .problem_list(&[number])
+            .build()?;
+        scraper.fetch_one(year, number).await
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        let (Some(challenge), Some(problems)) = (self.challenge, &self.problems) else {
+            return Ok(());
+        };
+        check_problems(challenge, problems)
+    }
+}
+
+// problem numbers past the contest's last problem are typos, not pages to
+// request; checked on `build` and again before scraping, since a config
+// file never goes through the builder
+fn check_problems(
+    challenge: Challenge,
+    problems: &[RangeInclusive<u32>],
+) -> std::result::Result<(), String> {
+    let count = challenge.problem_count();
+    match problems.iter().find(|r| r.start() < &1 || r.end() > &count) {
+        Some(range) => Err(format!(
+            "{} has problems 1..={count}, got {}..={}",
+            challenge.label(),
+            range.start(),
+            range.end()
+        )),
+        None => Ok(()),
+    }
+}
+
+// `years` and `problems` in a config: a number, a range, or a list of either
+fn numbers<'de, D>(deserializer: D) -> std::result::Result<Vec<RangeInclusive<u32>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Numbers {
+        One(u32),
+        Range(RangeInclusive<u32>),
+        List(Vec<Numbers>),
+    }
+
+    fn flatten(numbers: Numbers, out: &mut Vec<RangeInclusive<u32>>) {
+        match numbers {
+            Numbers::One(n) => out.push(n..=n),
+            Numbers::Range(range) => out.push(range),
+            Numbers::List(list) => list.into_iter().for_each(|n| flatten(n, out)),
+        }
+    }
+
+    let mut out = vec![];
+    flatten(Numbers::deserialize(deserializer)?, &mut out);
+    Ok(out)
+}
+
+// flattened, keeping the first of any repeats
+fn dedup(ranges: &[RangeInclusive<u32>]) -> Vec<u32> {
+    let mut seen = BTreeSet::new();
+    ranges
+        .iter()
+        .cloned()
+        .flatten()
+        .filter(|n| seen.insert(*n))
+        .collect()
+}
+
+// every problem of every year, year by year
+fn tasks(years: &[u32], problems: &[u32]) -> Vec<(u32, u32)> {
+    years
+        .iter()
+        .flat_map(|&year| problems.iter().map(move |&problem| (year, problem)))
+        .collect()
+}
+
+fn default_base_url() -> String {
+    AOPS_ORIGIN.to_string()
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_retry_backoff() -> Duration {
+    Duration::from_millis(500)
+}
+
+fn default_request_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_max_concurrency() -> usize {
+    8
+}
+
+/// AoPS credentials. `Debug` never shows the password or cookie, so they
+/// stay out of logs.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Auth {
+    /// Log in through the site's login endpoint and keep the session it
+    /// hands back.
+    Login { username: String, password: String },
+    /// Reuse an existing session, as a `Cookie` header value copied from a
+    /// signed-in browser, e.g. `"aopssid=..."`.
+    Cookie(String),
+}
+
+// State shared by every fetch of one scrape.
+#[derive(Debug, Default)]
+struct ScrapeContext {
+    challenge: Challenge,
+    base_url: String,
+    deterministic: bool,
+    max_bytes: Option<u64>,
+    solution_subpages: bool,
+    lightweight: bool,
+    problems_only: bool,
+    keep_raw_html: bool,
+    problem_budget: Option<Duration>,
+    request_timeout: Option<Duration>,
+    cache_dir: Option<PathBuf>,
+    client: reqwest::Client,
+    max_retries: u32,
+    retry_backoff: Duration,
+    // `None` is unbounded
+    permits: Option<Semaphore>,
+    fail_fast: bool,
+    downloaded: AtomicU64,
+    progress: Option<mpsc::UnboundedSender<ScrapeProgress>>,
+}
+
+// How fetching a single problem ended, short of an error.
+#[derive(Debug)]
+enum Fetched {
+    // the page, the problem, and why its solutions subpage couldn't be used
+    Page(String, AopsProblem, Option<anyhow::Error>),
+    // never requested: the byte budget had run out
+    Skipped,
+    // gave up: the problem's time budget elapsed
+    Abandoned,
+    // the wiki has no such page (404)
+    Missing,
+}
+
+// How warming the cache for a single problem ended, short of an error.
+#[derive(Debug)]
+enum Warmed {
+    Cached,
+    // the byte budget had run out
+    Skipped,
+    // the wiki has no such page (404)
+    Missing,
+}
+
+/// Sent to `AopsScraper::progress` as each problem finishes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrapeProgress {
+    pub year: u32,
+    pub number: u32,
+    pub status: ProblemStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProblemStatus {
+    Scraped,
+    /// Not fetched, see `ScrapeReport::skipped`.
+    Skipped,
+    /// See `ScrapeReport::abandoned`.
+    Abandoned,
+    /// See `ScrapeReport::missing`.
+    Missing,
+    /// Failed to download or parse, with the error.
+    Failed(String),
+}
+
+impl AopsScraper {
+    pub async fn scrape(self) -> Result<AopsScrapeResult> {
+        check_problems(self.challenge, &self.problems).map_err(anyhow::Error::msg)?;
+        let ctx = Arc::new(self.context().await?);
+        let (years, too_early) = self.years();
+
+        let mut ret = Self::scrape_all(years, self.problems(), ctx.clone()).await?;
+        ret.report.years_before_contest = too_early;
+        if self.glossary {
+            ret.glossary =
+                Self::scrape_glossary(glossary_links(&ret.contents, &ctx.base_url), ctx.clone())
+                    .await;
+        }
+        if self.inline_styles {
+            let (css, errors) = inline_stylesheets(&ret.styles, &ctx).await;
+            ret.inline_styles = Some(css);
+            ret.style_errors = errors;
+        }
+        if let Some(dir) = &self.assets_dir {
+            download_images(&mut ret.contents, dir, ctx.clone()).await?;
+        }
+        ret.report.bytes_downloaded = ctx.downloaded.load(Ordering::Relaxed);
+        ret.report.count_images(&ret.contents);
+        Ok(ret)
+    }
+
+    /// Download every configured page into `cache_dir` without parsing or
+    /// rendering anything, so a later `scrape` can run offline. Pages already
+    /// in the cache are not fetched again. Problems not fetched because of
+    /// `max_bytes` are listed in the report's `skipped`, those the wiki
+    /// doesn't have in `missing`, and those that failed to download in
+    /// `errors` (unless `fail_fast` is set).
+    pub async fn warm_cache(self) -> Result<ScrapeReport> {
+        anyhow::ensure!(self.cache_dir.is_some(), "warm_cache needs a cache_dir");
+        check_problems(self.challenge, &self.problems).map_err(anyhow::Error::msg)?;
+        let ctx = Arc::new(self.context().await?);
+        let (years, too_early) = self.years();
+        let mut report = ScrapeReport {
+            years_before_contest: too_early,
+            ..Default::default()
+        };
+        let mut handles = vec![];
+        let mut warmed = vec![];
+        for (year, problem) in tasks(&years, &self.problems()) {
+            let task = Self::warm_problem_with_permit(year, problem, ctx.clone());
+            if ctx.deterministic {
+                warmed.push(((year, problem), task.await));
+            } else {
+                handles.push(((year, problem), tokio::spawn(task)));
+            }
+        }
+
+        for (key, handle) in handles {
+            warmed.push((key, handle.await?));
+        }
+
+        for ((year, number), warm) in warmed {
+            match warm {
+                Ok(Warmed::Cached) => {}
+                Ok(Warmed::Skipped) => report.skipped.push((year, number)),
+                Ok(Warmed::Missing) => report.missing.push((year, number)),
+                Err(e) if ctx.fail_fast => return Err(e),
+                Err(e) => report.errors.push(ScrapeError {
+                    year,
+                    number,
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+        report.bytes_downloaded = ctx.downloaded.load(Ordering::Relaxed);
+        Ok(report)
+    }
+
+    /// Each problem as soon as it's fetched and parsed, in no particular
+    /// order (in order with `deterministic`), for writing out results while
+    /// the rest are still downloading. Problems that fail come through as
+    /// errors; those skipped, abandoned or missing don't come through at all.
+    /// Stylesheets, the glossary and the report are only assembled by
+    /// `scrape`.
+    pub async fn stream(self) -> Result<impl Stream<Item = Result<AopsProblem>>> {
+        check_problems(self.challenge, &self.problems).map_err(anyhow::Error::msg)?;
+        let ctx = Arc::new(self.context().await?);
+        let (years, _) = self.years();
+        let keys = tasks(&years, &self.problems());
+
+        let fetched = if ctx.deterministic {
+            futures::stream::iter(keys)
+                .then(move |(year, problem)| {
+                    let fetch = Self::fetch_problem_within_budget(year, problem, ctx.clone());
+                    async move { ((year, problem), fetch.await) }
+                })
+                .boxed()
+        } else {
+            keys.into_iter()
+                .map(|(year, problem)| {
+                    let handle = tokio::spawn(Self::fetch_problem_within_budget(
+                        year,
+                        problem,
+                        ctx.clone(),
+                    ));
+                    async move {
+                        let fetched = handle.await.map_err(anyhow::Error::from);
+                        ((year, problem), fetched.and_then(|fetched| fetched))
+                    }
+                })
+                .collect::<FuturesUnordered<_>>()
+                .boxed()
+        };
+
+        Ok(fetched.filter_map(|((year, number), fetched)| async move {
+            match fetched {
+                Ok(Fetched::Page(_, problem, _)) => Some(Ok(problem)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e.context(format!("{year} problem {number}")))),
+            }
+        }))
+    }
+
+    /// Fetch and parse just problem `number` of `challenge`'s `year` with the
+    /// default settings. See `AopsScraperBuilder::scrape_one` to configure
+    /// the fetch.
+    pub async fn scrape_one(year: u32, number: u32, challenge: Challenge) -> Result<AopsProblem> {
+        AopsScraperBuilder::default()
+            .scrape_one(year, number, challenge)
+            .await
+    }
+
+    async fn fetch_one(&self, year: u32, number: u32) -> Result<AopsProblem> {
+        let ctx = Arc::new(self.context().await?);
+        let fetched = Self::fetch_problem_within_budget(year, number, ctx)
+            .await
+            .with_context(|| format!("{year} problem {number}"))?;
+        match fetched {
+            Fetched::Page(_, problem, _) => Ok(problem),
+            Fetched::Skipped => anyhow::bail!("{year} problem {number} exceeds max_bytes"),
+            Fetched::Abandoned => anyhow::bail!("{year} problem {number} exceeded problem_budget"),
+            Fetched::Missing => anyhow::bail!("{year} problem {number} is not on the wiki"),
+        }
+    }
+
+    // the configured years: those the contest was held in, and those before
+    // it started, which are never requested
+    fn years(&self) -> (Vec<u32>, Vec<u32>) {
+        let first = self.challenge.first_year();
+        dedup(&self.years)
+            .into_iter()
+            .partition(|&year| year >= first)
+    }
+
+    fn problems(&self) -> Vec<u32> {
+        dedup(&self.problems)
+    }
+
+    // also signs in, if configured
+    async fn context(&self) -> Result<ScrapeContext> {
+        if let Some(dir) = &self.cache_dir {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let (client, jar) = match &self.client {
+            Some(client) => (client.clone(), None),
+            None => {
+                let jar = Arc::new(Jar::default());
+                let client = reqwest::Client::builder()
+                    .user_agent(USER_AGENT)
+                    .cookie_provider(jar.clone())
+                    .build()?;
+                (client, Some(jar))
+            }
+        };
+        if let Some(auth) = &self.auth {
+            auth.sign_in(&client, jar.as_deref(), &self.base_url)
+                .await?;
+        }
+
+        Ok(ScrapeContext {
+            challenge: self.challenge,
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            deterministic: self.deterministic,
+            max_bytes: self.max_bytes,
+            solution_subpages: self.solution_subpages,
+            lightweight: self.lightweight,
+            problems_only: self.problems_only,
+            keep_raw_html: self.keep_raw_html,
+            problem_budget: self.problem_budget,
+            request_timeout: (!self.request_timeout.is_zero()).then_some(self.request_timeout),
+            cache_dir: self.cache_dir.clone(),
+            client,
+            max_retries: self.max_retries,
+            retry_backoff: self.retry_backoff,
+            permits: (self.max_concurrency > 0).then(|| Semaphore::new(self.max_concurrency)),
+            fail_fast: self.fail_fast,
+            downloaded: AtomicU64::new(0),
+            progress: self.progress.clone(),
+        })
+    }
+
+    async fn warm_problem_with_permit(
+        year: u32,
+        problem: u32,
+        ctx: Arc<ScrapeContext>,
+    ) -> Result<Warmed> {
+        ctx.with_permit(Self::warm_problem(year, problem, ctx.clone()))
+            .await?
+    }
+
+    async fn warm_problem(year: u32, problem: u32, ctx: Arc<ScrapeContext>) -> Result<Warmed> {
+        let url = ctx.page_url(get_url(&ctx.base_url, year, problem, ctx.challenge));
+        let name = cache_name(year, problem, ctx.challenge);
+        match ctx.fetch_cached(&name, &url).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Ok(Warmed::Skipped),
+            Err(e) if is_not_found(&e) => return Ok(Warmed::Missing),
+            Err(e) => return Err(e),
+        }
+        if ctx.solution_subpages {
+            let url = ctx.page_url(get_solutions_url(
+                &ctx.base_url,
+                year,
+                problem,
+                ctx.challenge,
+            ));
+            let _ = ctx.fetch_cached(&format!("{name}_Solutions"), &url).await;
+        }
+        Ok(Warmed::Cached)
+    }
+
+    // best effort: pages that fail to load or have no lead paragraph are skipped
+    async fn scrape_glossary(
+        links: Vec<(String, String)>,
+        ctx: Arc<ScrapeContext>,
+    ) -> Vec<GlossaryEntry> {
+        let mut handles = vec![];
+        for (title, url) in links {
+            let ctx = ctx.clone();
+            let handle = tokio::spawn(async move {
+                let html = ctx.fetch(&url).await?;
+                Ok::<_, anyhow::Error>(
+                    html.and_then(|html| parse_glossary_entry(title, url, &html)),
+                )
+            });
+            handles.push(handle);
+        }
+
+        let mut entries = vec![];
+        for handle in handles {
+            if let Ok(Ok(Some(entry))) = handle.await {
+                entries.push(entry);
+            }
+        }
+        entries
+    }
+
+    // every (year, problem) is fetched as one task set, then regrouped by
+    // year, in year order
+    async fn scrape_all(
+        years: Vec<u32>,
+        problems: Vec<u32>,
+        ctx: Arc<ScrapeContext>,
+    ) -> Result<AopsScrapeResult> {
+        let mut contents = years
+            .iter()
+            .map(|&year| AopsContent::new(year))
+            .collect::<Vec<_>>();
+        let mut styles = vec![];
+        let mut report = ScrapeReport::default();
+        let mut errors = vec![];
+        let mut handles = vec![];
+        let mut fetched = vec![];
+        for (year, problem) in tasks(&years, &problems) {
+            let task = Self::fetch_problem_within_budget(year, problem, ctx.clone());
+            if ctx.deterministic {
+                fetched.push(((year, problem), task.await));
+            } else {
+                handles.push(((year, problem), tokio::spawn(task)));
+            }
+        }
+
+        for (key, handle) in handles {
+            fetched.push((key, handle.await?));
+        }
+
+        for ((year, number), page) in fetched {
+            let page = match page {
+                Ok(page) => page,
+                Err(e) if ctx.fail_fast => return Err(e),
+                Err(e) => {
+                    errors.push(ScrapeError {
+                        year,
+                        number,
+                        error: format!("{e:#}"),
+                    });
+                    continue;
+                }
+            };
+            let (html, problem) = match page {
+                Fetched::Page(html, problem, subpage_error) => {
+                    if let Some(e) = subpage_error {
+                        errors.push(ScrapeError {
+                            year,
+                            number,
+                            error: format!("{e:#}"),
+                        });
+                    }
+                    (html, problem)
+                }
+                Fetched::Skipped => {
+                    report.skipped.push((year, number));
+                    continue;
+                }
+                Fetched::Abandoned => {
+                    report.abandoned.push((year, number));
+                    continue;
+                }
+                Fetched::Missing => {
+                    report.missing.push((year, number));
+                    continue;
+                }
+            };
+            if let Some(content) = contents.iter_mut().find(|c| c.year == year) {
+                content.problems.push(problem);
+                content.problems.sort_by_key(|p| p.number);
+            }
+
+            if !ctx.lightweight {
+                merge_styles(&mut styles, get_stylesheets(&html)?);
+            }
+        }
+
+        // no empty sections for years whose problems all failed or were
+        // missing or skipped, and reproducible output whatever order the
+        // years were configured in
+        contents.retain(|c| !c.problems.is_empty());
+        contents.sort_by_key(|c| c.year);
+        Ok(AopsScrapeResult {
+            styles,
+            challenge: ctx.challenge,
+            contents,
+            report,
+            errors,
+            ..Default::default()
+        })
+    }
+
+    // bounds the whole fetch of a problem, subpages included, by
+    // `problem_budget`, which only starts once the problem's turn comes
+    // under `max_concurrency`
+    async fn fetch_problem_within_budget(
+        year: u32,
+        problem: u32,
+        ctx: Arc<ScrapeContext>,
+    ) -> Result<Fetched> {
+        let fetch = within_budget(
+            ctx.problem_budget,
+            Self::fetch_problem(year, problem, ctx.clone()),
+        );
+        let fetched = ctx.with_permit(fetch).await.and_then(|fetched| fetched);
+        if let Some(progress) = &ctx.progress {
+            let status = match &fetched {
+                Ok(Fetched::Page(..)) => ProblemStatus::Scraped,
+                Ok(Fetched::Skipped) => ProblemStatus::Skipped,
+                Ok(Fetched::Abandoned) => ProblemStatus::Abandoned,
+                Ok(Fetched::Missing) => ProblemStatus::Missing,
+                Err(e) => ProblemStatus::Failed(format!("{e:#}")),
+            };
+            let _ = progress.send(ScrapeProgress {
+                year,
+                number: problem,
+                status,
+            });
+        }
+        fetched
+    }
+
+    async fn fetch_problem(year: u32, problem: u32, ctx: Arc<ScrapeContext>) -> Result<Fetched> {
+        let url = ctx.page_url(get_url(&ctx.base_url, year, problem, ctx.challenge));
+        let name = cache_name(year, problem, ctx.challenge);
+        let html = match ctx.fetch_cached(&name, &url).await {
+            Ok(Some(html)) => html,
+            Ok(None) => return Ok(Fetched::Skipped),
+            Err(e) if is_not_found(&e) => return Ok(Fetched::Missing),
+            Err(e) => return Err(e),
+        };
+
+        let mut parsed = parse_html(year, problem, ctx.challenge, &html, ctx.problems_only)?;
+        if ctx.keep_raw_html {
+            parsed.raw_html = Some(html.clone());
+        }
+        let mut subpage_error = None;
+        if ctx.solution_subpages && !ctx.problems_only {
+            // most problems have no such page; that's not an error
+            let url = ctx.page_url(get_solutions_url(
+                &ctx.base_url,
+                year,
+                problem,
+                ctx.challenge,
+            ));
+            if let Ok(Some(page)) = ctx.fetch_cached(&format!("{name}_Solutions"), &url).await {
+                // the problem itself is still good
+                match parse_solutions_page(year, problem, &page) {
+                    Ok(solutions) => parsed.add_solutions(solutions),
+                    Err(e) => subpage_error = Some(e),
+                }
+            }
+        }
+
+        Ok(Fetched::Page(html, parsed, subpage_error))
+    }
+}
+
+// the stylesheets' contents, in order, with their `url(...)`s made absolute
+// so fonts and images still resolve once the CSS is moved into the page; and
+// the sheets that failed to download, which are left out
+async fn inline_stylesheets(
+    styles: &[String],
+    ctx: &ScrapeContext,
+) -> (String, Vec<(String, String)>) {
+    let mut css = String::new();
+    let mut errors = vec![];
+    for style in styles {
+        let url = absolute_url(style);
+        let sheet = async {
+            let base = url.parse::<reqwest::Url>()?;
+            Ok::<_, anyhow::Error>(ctx.fetch(&url).await?.map(|s| absolute_css_urls(&s, &base)))
+        };
+        match sheet.await {
+            Ok(Some(sheet)) => css.push_str(&format!("/* {url} */\n{sheet}\n")),
+            Ok(None) => errors.push((url, "byte budget exhausted".to_string())),
+            Err(e) => errors.push((url, format!("{e:#}"))),
+        }
+    }
+    (css, errors)
+}
+
+fn absolute_css_urls(css: &str, base: &reqwest::Url) -> String {
+    let mut out = String::new();
+    let mut rest = css;
+    while let Some(start) = rest.find("url(") {
+        let (head, tail) = rest.split_at(start + "url(".len());
+        out.push_str(head);
+        let Some(end) = tail.find(')') else {
+            rest = tail;
+            break;
+        };
+        let target = tail[..end].trim().trim_matches(|c| c == '"' || c == '\'');
+        match base.join(target) {
+            Ok(url) if !target.starts_with("data:") => out.push_str(&format!("\"{url}\"")),
+            _ => out.push_str(&tail[..end]),
+        }
+        rest = &tail[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Save each distinct remote image once, then rewrite the `src`s that were
+// saved. `srcset` is dropped from those, as it would still point at AoPS.
+async fn download_images(
+    contents: &mut [AopsContent],
+    dir: &Path,
+    ctx: Arc<ScrapeContext>,
+) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let img = Selector::parse("img[src]").unwrap();
+    let mut urls = BTreeMap::new();
+    for problem in contents.iter().flat_map(|c| &c.problems) {
+        for html in problem.fragments() {
+            for el in Html::parse_fragment(html).select(&img) {
+                let src = el.value().attr("src").unwrap_or_default();
+                let url = absolute_url(src);
+                if url.starts_with("https://") || url.starts_with("http://") {
+                    urls.insert(src.to_string(), url);
+                }
+            }
+        }
+    }
+
+    let mut handles = vec![];
+    let mut saved = BTreeMap::new();
+    for url in urls.values().collect::<BTreeSet<_>>() {
+        let path = dir.join(asset_name(url));
+        if path.exists() {
+            saved.insert(url.clone(), path);
+            continue;
+        }
+        let (ctx, url) = (ctx.clone(), url.clone());
+        handles.push(tokio::spawn(async move {
+            let body = ctx.fetch_bytes(&url).await;
+            (url, path, body)
+        }));
+    }
+    for handle in handles {
+        if let (url, path, Ok(Some(body))) = handle.await? {
+            fs::write(&path, body)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            saved.insert(url, path);
+        }
+    }
+
+    let local = |src: &str| Some(saved.get(urls.get(src)?)?.to_string_lossy().into_owned());
+    for problem in contents.iter_mut().flat_map(|c| &mut c.problems) {
+        let rewrite = |html: &str| {
+            rewrite_elements(html, |el| {
+                let Some(path) = el.attr("src").and_then(local) else {
+                    return;
+                };
+                el.attrs.retain(|name, _| name.local.as_ref() != "srcset");
+                for (name, value) in el.attrs.iter_mut() {
+                    if name.local.as_ref() == "src" {
+                        *value = path.as_str().into();
+                    }
+                }
+            })
+        };
+        problem.problem = rewrite(&problem.problem);
+        for solution in &mut problem.solutions {
+            *solution = rewrite(solution);
+        }
+    }
+    Ok(())
+}
+
+// a file name unique to `url`, keeping its extension
+fn asset_name(url: &str) -> String {
+    // FNV-1a: stable across runs and Rust versions, unlike `DefaultHasher`
+    let hash = url.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    match path
+        .rsplit('/')
+        .next()
+        .and_then(|file| file.rsplit_once('.'))
+    {
+        Some((_, ext)) if !ext.is_empty() && ext.len() <= 5 => format!("{hash:016x}.{ext}"),
+        _ => format!("{hash:016x}"),
+    }
+}
+
+async fn within_budget(
+    budget: Option<Duration>,
+    fetch: impl Future<Output = Result<Fetched>>,
+) -> Result<Fetched> {
+    match budget {
+        Some(budget) => tokio::time::timeout(budget, fetch)
+            .await
+            .unwrap_or(Ok(Fetched::Abandoned)),
+        None => fetch.await,
+    }
+}
+
+// `backoff` doubled `attempt` times, capped at `MAX_RETRY_DELAY`
+fn retry_delay(backoff: Duration, attempt: u32) -> Duration {
+    backoff
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_RETRY_DELAY)
+}
+
+fn is_not_found(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .is_some_and(|status| status == reqwest::StatusCode::NOT_FOUND)
+}
+
+impl ScrapeContext {
+    fn page_url(&self, url: String) -> String {
+        if self.lightweight {
+            format!("{url}?action=render")
+        } else {
+            url
+        }
+    }
+
+    // Download `url`, or `None` if the byte budget is (or becomes) exhausted.
+    async fn fetch(&self, url: &str) -> Result<Option<String>> {
+        let body = self.fetch_bytes(url).await?;
+        Ok(body.map(|body| String::from_utf8_lossy(&body).into_owned()))
+    }
+
+    async fn fetch_bytes(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        if self.exhausted() {
+            return Ok(None);
+        }
+        // held until the body is read, unless the whole problem holds one
+        let _permit = match &self.permits {
+            Some(permits) if HOLDS_PERMIT.try_with(|_| ()).is_err() => {
+                Some(permits.acquire().await?)
+            }
+            _ => None,
+        };
+        let mut res = self.get(url).await?;
+        let mut body = vec![];
+        while let Some(chunk) = res.chunk().await? {
+            if !self.charge(chunk.len()) {
+                return Ok(None);
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(Some(body))
+    }
+
+    // Run `fut` holding one of the `max_concurrency` permits, which its
+    // requests then share, so time spent waiting for a permit isn't charged
+    // to the budget inside `fut`.
+    async fn with_permit<T>(&self, fut: impl Future<Output = T>) -> Result<T> {
+        let _permit = match &self.permits {
+            Some(permits) => Some(permits.acquire().await?),
+            None => None,
+        };
+        Ok(HOLDS_PERMIT.scope((), fut).await)
+    }
+
+    // GET `url`, retrying transient failures, timeouts included, with
+    // exponential backoff
+    async fn get(&self, url: &str) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let mut req = self.client.get(url);
+            if let Some(timeout) = self.request_timeout {
+                req = req.timeout(timeout);
+            }
+            let res = req.send().await;
+            let transient = match &res {
+                Ok(res) => {
+                    res.status().is_server_error()
+                        || res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !transient || attempt >= self.max_retries {
+                return Ok(res?.error_for_status()?);
+            }
+            tokio::time::sleep(retry_delay(self.retry_backoff, attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    // `fetch`, reading from and saving to the cache when there is one. `name`
+    // is the cache file name without extension.
+    async fn fetch_cached(&self, name: &str, url: &str) -> Result<Option<String>> {
+        let Some(dir) = &self.cache_dir else {
+            return self.fetch(url).await;
+        };
+        // `?action=render` pages are kept apart from full ones
+        let suffix = if self.lightweight { ".render" } else { "" };
+        let path = dir.join(format!("{name}{suffix}.html"));
+        if let Ok(html) = fs::read_to_string(&path) {
+            return Ok(Some(html));
+        }
+
+        // write then rename, so an interrupted run never leaves a truncated
+        // page that later runs would trust
+        let html = self.fetch(url).await?;
+        if let Some(html) = &html {
+            let tmp = path.with_extension("html.tmp");
+            fs::write(&tmp, html)
+                .and_then(|_| fs::rename(&tmp, &path))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(html)
+    }
+
+    // count `len` more downloaded bytes; false once that goes over `max_bytes`
+    fn charge(&self, len: usize) -> bool {
+        let total = self.downloaded.fetch_add(len as u64, Ordering::Relaxed) + len as u64;
+        self.max_bytes.is_none_or(|max| total <= max)
+    }
+
+    fn exhausted(&self) -> bool {
+        self.max_bytes
+            .is_some_and(|max| self.downloaded.load(Ordering::Relaxed) >= max)
+    }
+}
+
+impl Auth {
+    // leaves the session cookie in the client's store; `jar` is that store,
+    // if it's the scraper's own
+    async fn sign_in(&self, client: &reqwest::Client, jar: Option<&Jar>, base: &str) -> Result<()> {
+        let base = base.trim_end_matches('/');
+        let origin = base.parse::<reqwest::Url>()?;
+        let (username, password) = match self {
+            Auth::Cookie(cookie) => {
+                let jar = jar.context("Auth::Cookie can't be used with a custom client")?;
+                for pair in cookie.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+                    jar.add_cookie_str(pair, &origin);
+                }
+                return Ok(());
+            }
+            Auth::Login { username, password } => (username, password),
+        };
+
+        // the same request the site's login form sends; failures come back as
+        // a 200 with an `error_code`
+        let res: serde_json::Value = client
+            .post(format!("{base}/ajax.php"))
+            .form(&[
+                ("a", "login"),
+                ("username", username.as_str()),
+                ("password", password.as_str()),
+                ("stay", "false"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Unexpected AoPS login response")?;
+        if res.get("error_code").is_some() {
+            let msg = res["error_msg"].as_str().unwrap_or("unknown error");
+            anyhow::bail!("AoPS login failed for {username}: {msg}");
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::Login { username, .. } => f
+                .debug_struct("Login")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            Auth::Cookie(_) => f.debug_tuple("Cookie").field(&"<redacted>").finish(),
+        }
+    }
+}
+
+fn get_url(base: &str, year: u32, problem: u32, challenge: Challenge) -> String {
+    format!(
+        "{}/wiki/index.php/{}_{}_Problems/Problem_{}",
+        base.trim_end_matches('/'),
+        year,
+        challenge,
+        problem
+    )
+}
+
+fn cache_name(year: u32, problem: u32, challenge: Challenge) -> String {
+    format!("{challenge}_{year}_{problem}")
+}
+
+fn get_solutions_url(base: &str, year: u32, problem: u32, challenge: Challenge) -> String {
+    format!("{}_Solutions", get_url(base, year, problem, challenge))
+}
+
+// (title, url) of every concept page linked from the solutions, deduplicated.
+// Links to other contests' problem pages, files or categories are not concepts.
+fn glossary_links(contents: &[AopsContent], base: &str) -> Vec<(String, String)> {
+    let selector = Selector::parse("a[href^='/wiki/index.php/'][title]").unwrap();
+    let mut links: Vec<(String, String)> = vec![];
+    let solutions = contents
+        .iter()
+        .flat_map(|c| &c.problems)
+        .flat_map(|p| &p.solutions);
+    for solution in solutions {
+        let fragment = Html::parse_fragment(solution);
+        for node in fragment.select(&selector) {
+            let href = node.value().attr("href").unwrap_or_default();
+            let page = &href["/wiki/index.php/".len()..];
+            if page.contains(':')
+                || page.contains("Problems")
+                || page.starts_with(|c: char| c.is_ascii_digit())
+            {
+                continue;
+            }
+            let url = format!("{base}{href}");
+            if links.iter().all(|(_, u)| *u != url) {
+                let title = node.value().attr("title").unwrap_or(page).to_string();
+                links.push((title, url));
+            }
+        }
+    }
+    links
+}
+
+fn parse_glossary_entry(title: String, url: String, html: &str) -> Option<GlossaryEntry> {
+    let fragment = Html::parse_document(html);
+    let summary = fragment
+        .select(&Selector::parse("div.mw-parser-output > p").unwrap())
+        .find(|p| p.text().any(|t| !t.trim().is_empty()))?
+        .html();
+    Some(GlossaryEntry {
+        title,
+        url,
+        summary,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::aops::tests::load_fixture;
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    // the saved page at `fixture`, as the wiki would send it
+    fn fixture_page(fixture: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_string(fs::read_to_string(fixture).unwrap())
+    }
+
+    // answer every request for `path` with the saved page at `fixture`
+    async fn serve_fixture(server: &MockServer, path: &str, fixture: &str) {
+        Mock::given(matchers::path(path))
+            .respond_with(fixture_page(fixture))
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn glossary_should_work() {
+        let mut problem = load_fixture(2003, 23, "fixtures/p23.html");
+        problem.solutions = vec![r#"<p>By the <a href="/wiki/index.php/Pythagorean_Theorem" title="Pythagorean Theorem">Pythagorean Theorem</a>,
+            see <a href="/wiki/index.php/2003_AMC_8_Problems" title="2003 AMC 8 Problems">Problems</a>
+            and <a href="/wiki/index.php/Pythagorean_Theorem" title="Pythagorean Theorem">again</a>.</p>"#
+            .to_string()];
+        let contents = vec![AopsContent {
+            year: 2003,
+            problems: vec![problem],
+        }];
+
+        let links = glossary_links(&contents, AOPS_ORIGIN);
+        assert_eq!(
+            links,
+            [(
+                "Pythagorean Theorem".to_string(),
+                format!("{AOPS_ORIGIN}/wiki/index.php/Pythagorean_Theorem")
+            )]
+        );
+
+        let html = fs::read_to_string("fixtures/pythagorean_theorem.html").unwrap();
+        let (title, url) = links.into_iter().next().unwrap();
+        let entry = parse_glossary_entry(title, url, &html).unwrap();
+        assert!(entry.summary.contains("states that for a"));
+
+        let mut ret = AopsScrapeResult {
+            contents,
+            glossary: vec![entry],
+            ..Default::default()
+        };
+        assert!(ret.generate_solution().unwrap().contains("Glossary"));
+        assert!(!ret.generate_problem().unwrap().contains("Glossary"));
+    }
+
+    #[test]
+    fn urls_should_work() {
+        assert_eq!(
+            get_url(AOPS_ORIGIN, 2019, 1, Challenge::AimeI),
+            "https://artofproblemsolving.com/wiki/index.php/2019_AIME_I_Problems/Problem_1"
+        );
+        assert_eq!(
+            get_url(AOPS_ORIGIN, 2020, 12, Challenge::Amc12b),
+            "https://artofproblemsolving.com/wiki/index.php/2020_AMC_12B_Problems/Problem_12"
+        );
+        assert_eq!(
+            get_solutions_url(AOPS_ORIGIN, 2005, 24, Challenge::Amc8),
+            "https://artofproblemsolving.com/wiki/index.php/2005_AMC_8_Problems/Problem_24_Solutions"
+        );
+
+        let ctx = ScrapeContext {
+            lightweight: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            ctx.page_url(get_url(AOPS_ORIGIN, 2003, 23, Challenge::Amc8)),
+            "https://artofproblemsolving.com/wiki/index.php/2003_AMC_8_Problems/Problem_23?action=render"
+        );
+    }
+
+    #[test]
+    fn byte_budget_should_work() {
+        let ctx = ScrapeContext {
+            max_bytes: Some(100),
+            ..Default::default()
+        };
+
+        assert!(ctx.charge(60));
+        assert!(!ctx.exhausted());
+        assert!(!ctx.charge(60));
+        assert!(ctx.exhausted());
+        assert_eq!(ctx.downloaded.load(Ordering::Relaxed), 120);
+    }
+
+    #[tokio::test]
+    async fn problem_budget_should_abandon_slow_problems() {
+        let stuck = std::future::pending();
+        let fetched = within_budget(Some(Duration::from_millis(10)), stuck)
+            .await
+            .unwrap();
+        assert!(matches!(fetched, Fetched::Abandoned));
+
+        let done = async { Ok(Fetched::Skipped) };
+        let fetched = within_budget(Some(Duration::from_secs(10)), done)
+            .await
+            .unwrap();
+        assert!(matches!(fetched, Fetched::Skipped));
+    }
+
+    #[tokio::test]
+    async fn request_timeout_should_apply_to_each_attempt() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path("/slow"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+            .mount(&server)
+            .await;
+
+        Mock::given(matchers::path("/stuck-once"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(matchers::path("/stuck-once"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>ok</p>"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let ctx = ScrapeContext {
+            request_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let err = ctx
+            .fetch(&format!("{}/slow", server.uri()))
+            .await
+            .unwrap_err();
+        assert!(err
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_timeout()));
+
+        // each attempt gets its own timeout, and a timed out one is retried
+        let ctx = ScrapeContext {
+            max_retries: 1,
+            retry_backoff: Duration::from_millis(1),
+            ..ctx
+        };
+        let body = ctx
+            .fetch(&format!("{}/stuck-once", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(body.as_deref(), Some("<p>ok</p>"));
+    }
+
+    #[tokio::test]
+    async fn queued_problems_should_not_time_out() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path_regex(
+            "^/wiki/index.php/2003_AMC_8_Problems/Problem_2[1-4]$",
+        ))
+        .respond_with(fixture_page("fixtures/p23.html").set_delay(Duration::from_millis(150)))
+        .mount(&server)
+        .await;
+
+        // one page at a time: the last waits ~450ms, each fetch takes 150ms
+        let ret = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2003])
+            .problems(21..=24)
+            .max_concurrency(1)
+            .request_timeout(Duration::from_millis(400))
+            .base_url(server.uri())
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+        assert!(ret.errors.is_empty(), "{:?}", ret.errors);
+        assert_eq!(ret.contents[0].problems.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn queued_problems_should_not_be_abandoned() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path_regex(
+            "^/wiki/index.php/2003_AMC_8_Problems/Problem_2[1-4]$",
+        ))
+        .respond_with(fixture_page("fixtures/p23.html").set_delay(Duration::from_millis(150)))
+        .mount(&server)
+        .await;
+
+        let ret = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2003])
+            .problems(21..=24)
+            .max_concurrency(1)
+            .problem_budget(Duration::from_millis(400))
+            .base_url(server.uri())
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+        assert!(
+            ret.report.abandoned.is_empty(),
+            "{:?}",
+            ret.report.abandoned
+        );
+        assert_eq!(ret.contents[0].problems.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn warm_cache_should_skip_cached_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::copy("fixtures/p23.html", dir.path().join("AMC_8_2003_23.html")).unwrap();
+        let scraper = || {
+            AopsScraperBuilder::default()
+                .challenge(Challenge::Amc8)
+                .years(&[2003..=2003])
+                .problems(23..=23)
+                .cache_dir(dir.path())
+                .build()
+                .unwrap()
+        };
+
+        let report = scraper().warm_cache().await.unwrap();
+        assert_eq!(report.bytes_downloaded, 0);
+        assert!(report.skipped.is_empty());
+
+        // and the cached page is all a scrape needs
+        let ret = scraper().scrape().await.unwrap();
+        assert_eq!(ret.report.bytes_downloaded, 0);
+        assert_eq!(ret.contents[0].problems[0].number, 23);
+        assert!(!ret.styles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn warm_cache_should_report_failures() {
+        let server = MockServer::start().await;
+        serve_fixture(
+            &server,
+            "/wiki/index.php/2003_AMC_8_Problems/Problem_23",
+            "fixtures/p23.html",
+        )
+        .await;
+        Mock::given(matchers::path(
+            "/wiki/index.php/2003_AMC_8_Problems/Problem_24",
+        ))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&server)
+        .await;
+
+        // problem 25 isn't mocked, so it's a 404
+        let dir = tempfile::tempdir().unwrap();
+        let report = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2003])
+            .problems(23..=25)
+            .cache_dir(dir.path())
+            .base_url(server.uri())
+            .build()
+            .unwrap()
+            .warm_cache()
+            .await
+            .unwrap();
+        assert!(dir.path().join("AMC_8_2003_23.html").exists());
+        assert_eq!(report.missing, [(2003, 25)]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!((report.errors[0].year, report.errors[0].number), (2003, 24));
+    }
+
+    #[test]
+    fn auth_should_not_leak_secrets() {
+        let config = r#"{
+            "years": [{"start": 2003, "end": 2003}],
+            "problems": {"start": 23, "end": 23},
+            "challenge": "AMC_8",
+            "auth": {"Login": {"username": "alice", "password": "hunter2"}}
+        }"#;
+        let scraper: AopsScraper = serde_json::from_str(config).unwrap();
+        let debug = format!("{scraper:?}");
+        assert!(debug.contains("alice"));
+        assert!(!debug.contains("hunter2"));
+
+        let auth = Auth::Cookie("aopssid=secret".to_string());
+        assert!(!format!("{auth:?}").contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn repeated_years_and_problems_should_be_fetched_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::copy("fixtures/p23.html", dir.path().join("AMC_8_2003_23.html")).unwrap();
+        let ret = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .year_list(&[2003, 2003])
+            .problem_list(&[23, 23])
+            .cache_dir(dir.path())
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+        assert_eq!(ret.contents.len(), 1);
+        assert_eq!(ret.contents[0].problems.len(), 1);
+        assert_eq!(ret.report.bytes_downloaded, 0);
+
+        let config = r#"{
+            "years": [2019, {"start": 2021, "end": 2022}, 2019],
+            "problems": [1, 5, 25],
+            "challenge": "AMC_8"
+        }"#;
+        let scraper: AopsScraper = serde_json::from_str(config).unwrap();
+        assert_eq!(scraper.years().0, vec![2019, 2021, 2022]);
+        assert_eq!(scraper.problems(), vec![1, 5, 25]);
+    }
+
+    #[tokio::test]
+    async fn problems_past_the_last_should_be_rejected() {
+        let err = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2003])
+            .problems(20..=30)
+            .build()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "AMC 8 has problems 1..=25, got 20..=30");
+
+        assert!(AopsScraperBuilder::default()
+            .challenge(Challenge::AimeI)
+            .years(&[2019..=2019])
+            .problem_list(&[1, 16])
+            .build()
+            .is_err());
+        assert!(AopsScraperBuilder::default()
+            .challenge(Challenge::AimeI)
+            .years(&[2019..=2019])
+            .problems(1..=15)
+            .build()
+            .is_ok());
+
+        let config = r#"{
+            "years": 2003,
+            "problems": {"start": 20, "end": 30},
+            "challenge": "AMC_8"
+        }"#;
+        let scraper: AopsScraper = serde_json::from_str(config).unwrap();
+        let err = scraper.scrape().await.unwrap_err();
+        assert_eq!(err.to_string(), "AMC 8 has problems 1..=25, got 20..=30");
+    }
+
+    #[tokio::test]
+    async fn unparsable_subpage_should_keep_the_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::copy("fixtures/p23.html", dir.path().join("AMC_8_2003_23.html")).unwrap();
+        fs::write(
+            dir.path().join("AMC_8_2003_23_Solutions.html"),
+            "<html><body>Service unavailable</body></html>",
+        )
+        .unwrap();
+        let ret = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2003])
+            .problems(23..=23)
+            .solution_subpages(true)
+            .cache_dir(dir.path())
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+        assert_eq!(ret.contents[0].problems[0].number, 23);
+        assert_eq!((ret.errors[0].year, ret.errors[0].number), (2003, 23));
+        assert!(ret.errors[0].error.contains("No solutions found"));
+    }
+
+    #[tokio::test]
+    async fn keep_raw_html_should_work() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::copy("fixtures/p23.html", dir.path().join("AMC_8_2003_23.html")).unwrap();
+        let scrape = |keep_raw_html| {
+            AopsScraperBuilder::default()
+                .challenge(Challenge::Amc8)
+                .years(&[2003..=2003])
+                .problems(23..=23)
+                .cache_dir(dir.path())
+                .keep_raw_html(keep_raw_html)
+                .build()
+                .unwrap()
+                .scrape()
+        };
+
+        let ret = scrape(false).await.unwrap();
+        assert_eq!(ret.contents[0].problems[0].raw_html(), None);
+
+        let ret = scrape(true).await.unwrap();
+        let raw = ret.contents[0].problems[0].raw_html().unwrap();
+        assert_eq!(raw, fs::read_to_string("fixtures/p23.html").unwrap());
+        assert!(!ret.to_json().unwrap().contains("raw_html"));
+    }
+
+    #[tokio::test]
+    async fn years_before_contest_should_be_skipped() {
+        assert_eq!(Challenge::Amc8.first_year(), 1999);
+        assert_eq!(Challenge::Amc10b.first_year(), 2002);
+
+        let ret = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc10a)
+            .years(&[1998..=2001])
+            .problems(1..=25)
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+        assert!(ret.contents.is_empty());
+        assert_eq!(
+            ret.report.years_before_contest,
+            vec![1998, 1999, 2000, 2001]
+        );
+        assert_eq!(ret.report.bytes_downloaded, 0);
+    }
+
+    #[tokio::test]
+    async fn contents_should_be_sorted_by_year() {
+        let server = MockServer::start().await;
+        // the earlier year finishes last
+        for (year, delay) in [(2019, 300), (2023, 0)] {
+            Mock::given(matchers::path(format!(
+                "/wiki/index.php/{year}_AMC_8_Problems/Problem_23"
+            )))
+            .respond_with(fixture_page("fixtures/p23.html").set_delay(Duration::from_millis(delay)))
+            .mount(&server)
+            .await;
+        }
+
+        let ret = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .year_list(&[2023, 2019])
+            .problems(23..=23)
+            .base_url(server.uri())
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+        let years = ret.contents.iter().map(|c| c.year).collect::<Vec<_>>();
+        assert_eq!(years, [2019, 2023]);
+        assert!(ret.contents.iter().all(|c| c.problems.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn scrape_one_should_work() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path(
+            "/wiki/index.php/2019_AIME_I_Problems/Problem_1",
+        ))
+        .respond_with(fixture_page("fixtures/2019aimeIp1.html"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+        let mut scraper = AopsScraperBuilder::default();
+        scraper.base_url(server.uri());
+        let problem = scraper.scrape_one(2019, 1, Challenge::AimeI).await.unwrap();
+        assert_eq!((problem.year, problem.number), (2019, 1));
+        assert_eq!(problem.challenge(), Challenge::AimeI);
+        assert!(!problem.solutions.is_empty());
+
+        assert!(scraper
+            .scrape_one(2019, 16, Challenge::AimeI)
+            .await
+            .is_err());
+        let err = scraper
+            .scrape_one(2019, 2, Challenge::AimeI)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "2019 problem 2 is not on the wiki");
+    }
+
+    #[tokio::test]
+    async fn base_url_should_point_at_a_mirror() {
+        assert_eq!(
+            get_url("http://mirror/", 2003, 23, Challenge::Amc8),
+            get_url("http://mirror", 2003, 23, Challenge::Amc8)
+        );
+
+        let server = MockServer::start().await;
+        Mock::given(matchers::path(
+            "/wiki/index.php/2003_AMC_8_Problems/Problem_23",
+        ))
+        .respond_with(fixture_page("fixtures/p23.html"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+        let ret = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2003])
+            .problems(23..=23)
+            .base_url(format!("{}/", server.uri()))
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+        assert!(ret.errors.is_empty());
+        assert_eq!(ret.contents[0].problems[0].number, 23);
+    }
+
+    #[tokio::test]
+    async fn missing_problems_should_be_skipped() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path_regex(
+            "^/wiki/index.php/2003_AMC_8_Problems/Problem_2[1-4]$",
+        ))
+        .respond_with(fixture_page("fixtures/p23.html"))
+        .mount(&server)
+        .await;
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let ret = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2004])
+            .problems(21..=25)
+            .base_url(server.uri())
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+        assert!(ret.errors.is_empty());
+        // 2004 has nothing left to show
+        assert_eq!(ret.contents.len(), 1);
+        assert_eq!(ret.contents[0].problems.len(), 4);
+        assert_eq!(ret.report.missing.len(), 6);
+        assert!(ret.report.missing.contains(&(2003, 25)));
+    }
+
+    #[tokio::test]
+    async fn stream_should_yield_every_problem_once() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::any())
+            .respond_with(fixture_page("fixtures/p23.html"))
+            .mount(&server)
+            .await;
+
+        let problems: Vec<_> = AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2004])
+            .problems(21..=25)
+            .base_url(server.uri())
+            .build()
+            .unwrap()
+            .stream()
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        let mut keys: Vec<_> = problems
+            .into_iter()
+            .map(|p| p.map(|p| (p.year, p.number)).unwrap())
+            .collect();
+        keys.sort();
+        let expected: Vec<_> = (2003..=2004)
+            .flat_map(|year| (21..=25).map(move |n| (year, n)))
+            .collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[tokio::test]
+    async fn progress_should_report_every_problem() {
+        let server = MockServer::start().await;
+        serve_fixture(
+            &server,
+            "/wiki/index.php/2003_AMC_8_Problems/Problem_23",
+            "fixtures/p23.html",
+        )
+        .await;
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>not a wiki page</p>"))
+            .mount(&server)
+            .await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        AopsScraperBuilder::default()
+            .challenge(Challenge::Amc8)
+            .years(&[2003..=2003])
+            .problems(21..=25)
+            .base_url(server.uri())
+            .progress(tx)
+            .build()
+            .unwrap()
+            .scrape()
+            .await
+            .unwrap();
+
+        let mut events = vec![];
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        events.sort_by_key(|e| e.number);
+        let numbers: Vec<_> = events.iter().map(|e| (e.year, e.number)).collect();
+        assert_eq!(numbers, (21..=25).map(|n| (2003, n)).collect::<Vec<_>>());
+        assert_eq!(events[2].status, ProblemStatus::Scraped);
+        assert!(matches!(events[0].status, ProblemStatus::Failed(_)));
+    }
+
+    #[tokio::test]
+    async fn fetch_should_retry_transient_failures() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path("/flaky"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(matchers::path("/flaky"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>ok</p>"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(matchers::path("/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let ctx = ScrapeContext {
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let body = ctx.fetch(&format!("{}/flaky", server.uri())).await.unwrap();
+        assert_eq!(body.as_deref(), Some("<p>ok</p>"));
+        assert!(ctx
+            .fetch(&format!("{}/missing", server.uri()))
+            .await
+            .is_err());
+
+        let backoff = Duration::from_millis(500);
+        assert_eq!(retry_delay(backoff, 0), backoff);
+        assert_eq!(retry_delay(backoff, 2), Duration::from_secs(2));
+        assert_eq!(retry_delay(backoff, 40), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(Duration::MAX, 3), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test]
+    async fn requests_should_use_the_configured_client() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path("/custom"))
+            .and(matchers::header("user-agent", "my-tool/1.0"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>custom</p>"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(matchers::path("/default"))
+            .and(matchers::header("user-agent", USER_AGENT))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>default</p>"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let builder = || {
+            let mut builder = AopsScraperBuilder::default();
+            builder
+                .challenge(Challenge::Amc8)
+                .years(&[2003..=2003])
+                .problems(23..=23);
+            builder
+        };
+        let client = reqwest::Client::builder()
+            .user_agent("my-tool/1.0")
+            .build()
+            .unwrap();
+        let ctx = builder()
+            .client(client)
+            .build()
+            .unwrap()
+            .context()
+            .await
+            .unwrap();
+        let body = ctx
+            .fetch(&format!("{}/custom", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(body.as_deref(), Some("<p>custom</p>"));
+
+        let ctx = builder().build().unwrap().context().await.unwrap();
+        let body = ctx
+            .fetch(&format!("{}/default", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(body.as_deref(), Some("<p>default</p>"));
+    }
+
+    #[tokio::test]
+    async fn max_concurrency_should_limit_in_flight_requests() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let in_flight = Arc::new(AtomicU64::new(0));
+        let peak = Arc::new(AtomicU64::new(0));
+        let (current, max) = (in_flight.clone(), peak.clone());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let (current, max) = (current.clone(), max.clone());
+                tokio::spawn(async move {
+                    let mut buf = [0; 1024];
+                    let _ = socket.read(&mut buf).await;
+                    max.fetch_max(current.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    current.fetch_sub(1, Ordering::SeqCst);
+                    let res = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok";
+                    let _ = socket.write_all(res.as_bytes()).await;
+                });
+            }
+        });
+
+        let ctx = Arc::new(ScrapeContext {
+            permits: Some(Semaphore::new(2)),
+            ..Default::default()
+        });
+        let handles: Vec<_> = (0..6)
+            .map(|i| {
+                let ctx = ctx.clone();
+                tokio::spawn(async move { ctx.fetch(&format!("http://{addr}/{i}")).await })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.await.unwrap().unwrap().as_deref(), Some("ok"));
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_problems_should_not_abort_the_scrape() {
+        let dir = tempfile::tempdir().unwrap();
+        for number in [21, 22, 24, 25] {
+            let name = format!("AMC_8_2003_{number}.html");
+            fs::copy("fixtures/p23.html", dir.path().join(name)).unwrap();
+        }
+        // a page without a solution section can't be parsed
+        let broken = dir.path().join("AMC_8_2003_23.html");
+        fs::copy("fixtures/pythagorean_theorem.html", broken).unwrap();
+        let scraper = |fail_fast| {
+            AopsScraperBuilder::default()
+                .challenge(Challenge::Amc8)
+                .years(&[2003..=2003])
+                .problems(21..=25)
+                .cache_dir(dir.path())
+                .fail_fast(fail_fast)
+                .build()
+                .unwrap()
+        };
+
+        let ret = scraper(false).scrape().await.unwrap();
+        let numbers: Vec<_> = ret.contents[0].problems.iter().map(|p| p.number).collect();
+        assert_eq!(numbers, vec![21, 22, 24, 25]);
+        assert_eq!(ret.errors.len(), 1);
+        assert_eq!((ret.errors[0].year, ret.errors[0].number), (2003, 23));
+        assert!(ret.errors[0].error.contains("No solution found"));
+
+        assert!(scraper(true).scrape().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cache_should_avoid_refetching() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path("/cached"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(matchers::path("/fresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>fresh</p>"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let name = cache_name(2003, 23, Challenge::Amc8);
+        assert_eq!(name, "AMC_8_2003_23");
+        assert_ne!(name, cache_name(2003, 23, Challenge::Amc10a));
+        fs::write(dir.path().join(format!("{name}.html")), "<p>cached</p>").unwrap();
+        let ctx = ScrapeContext {
+            cache_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let url = format!("{}/cached", server.uri());
+        let cached = ctx.fetch_cached(&name, &url).await.unwrap();
+        assert_eq!(cached.as_deref(), Some("<p>cached</p>"));
+
+        // a miss is fetched once, then served from the cache
+        let url = format!("{}/fresh", server.uri());
+        for _ in 0..2 {
+            let fresh = ctx.fetch_cached("AMC_8_2003_24", &url).await.unwrap();
+            assert_eq!(fresh.as_deref(), Some("<p>fresh</p>"));
+        }
+        assert!(dir.path().join("AMC_8_2003_24.html").exists());
+    }
+
+    #[tokio::test]
+    async fn inline_styles_should_work() {
+        let server = MockServer::start().await;
+        let css = ".mw-headline { color: teal; background: url(../img/bg.png); }";
+        Mock::given(matchers::path("/assets/css/main.css"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(css))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let styles = vec![format!("{}/assets/css/main.css", server.uri())];
+        let (inline, errors) = inline_stylesheets(&styles, &ScrapeContext::default()).await;
+        assert!(errors.is_empty());
+        assert!(inline.contains(".mw-headline { color: teal;"));
+        assert!(inline.contains(&format!(r#"url("{}/assets/img/bg.png")"#, server.uri())));
+
+        let mut ret = AopsScrapeResult {
+            styles,
+            inline_styles: Some(inline),
+            contents: vec![AopsContent {
+                year: 2003,
+                problems: vec![load_fixture(2003, 23, "fixtures/p23.html")],
+            }],
+            ..Default::default()
+        };
+        let html = ret.generate_problem().unwrap();
+        assert!(html.contains("color: teal"));
+        assert!(!html.contains(r#"<link rel="stylesheet""#));
+
+        // relative hrefs are fetched from the AoPS origin
+        assert_eq!(
+            absolute_url("/load.php?only=styles"),
+            "https://artofproblemsolving.com/load.php?only=styles"
+        );
+        let base = "https://example.com/a/b.css".parse().unwrap();
+        let data = "url(data:image/png;base64,AAAA)";
+        assert_eq!(absolute_css_urls(data, &base), data);
+    }
+
+    #[tokio::test]
+    async fn missing_stylesheets_should_be_reported() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path("/assets/css/main.css"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(".toc { display: none; }"))
+            .mount(&server)
+            .await;
+        Mock::given(matchers::path("/assets/css/stale.css"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let good = format!("{}/assets/css/main.css", server.uri());
+        let bad = format!("{}/assets/css/stale.css", server.uri());
+        let (inline, errors) =
+            inline_stylesheets(&[bad.clone(), good], &ScrapeContext::default()).await;
+        assert!(inline.contains(".toc { display: none; }"));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, bad);
+        assert!(errors[0].1.contains("404"));
+
+        let ctx = ScrapeContext {
+            max_bytes: Some(0),
+            ..Default::default()
+        };
+        let (inline, errors) = inline_stylesheets(std::slice::from_ref(&bad), &ctx).await;
+        assert!(inline.is_empty());
+        assert_eq!(errors, vec![(bad, "byte budget exhausted".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn download_images_should_work() {
+        let server = MockServer::start().await;
+        let png = vec![0x89, b'P', b'N', b'G', 0xff, 0x00];
+        Mock::given(matchers::path("/latex/a.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(png.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let remote = format!("{}/latex/a.png", server.uri());
+        let data = "data:image/png;base64,AAAA";
+        let mut problem = load_fixture(2003, 23, "fixtures/p23.html");
+        problem.problem = format!(r#"<img src="{remote}" srcset="{remote} 2x"><img src="{data}">"#);
+        problem.solutions = vec![format!(
+            r#"<img src="{remote}"><img src="assets/local.png">"#
+        )];
+        let mut contents = vec![AopsContent {
+            year: 2003,
+            problems: vec![problem],
+        }];
+
+        let dir = tempfile::tempdir().unwrap();
+        let assets = dir.path().join("assets");
+        download_images(&mut contents, &assets, Arc::new(ScrapeContext::default()))
+            .await
+            .unwrap();
+
+        let local = assets.join(asset_name(&remote));
+        assert!(local.to_string_lossy().ends_with(".png"));
+        assert_eq!(fs::read(&local).unwrap(), png);
+        let problem = &contents[0].problems[0];
+        let src = format!(r#"src="{}""#, local.display());
+        assert!(problem.problem.contains(&src));
+        assert!(!problem.problem.contains("srcset"));
+        assert!(problem.problem.contains(data));
+        assert!(problem.solutions[0].contains(&src));
+        assert!(problem.solutions[0].contains(r#"src="assets/local.png""#));
+    }
+}
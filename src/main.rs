@@ -1,20 +1,242 @@
-use anyhow::Result;
-use html_concat::aops::{AopsScraperBuilder, Challenge};
-use std::fs;
+use anyhow::{Context, Result};
+use clap::Parser;
+use html_concat::aops::{self, AopsScraper, AopsScraperBuilder, Challenge};
+use notify::{RecursiveMode, Watcher};
+use std::{
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Parser)]
+struct Args {
+    /// Scraper config as JSON (`years`, `problems`, `challenge`; years and
+    /// problems are numbers, ranges or lists of either). Without one, the
+    /// scrape is set up by `--challenge`, `--years` and `--problems`.
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Contest to scrape, e.g. `AMC_10A` or `AIME_I`.
+    #[arg(long, default_value = "AMC_8", conflicts_with = "config")]
+    challenge: Challenge,
+    /// Years to scrape, as numbers and ranges, e.g. `2019-2021,2023`.
+    #[arg(long, default_value = "2003-2020,2022-2023", conflicts_with = "config", value_parser = parse_numbers)]
+    years: Numbers,
+    /// Problem numbers to scrape in each year, e.g. `21-25` or `1,5,25`.
+    #[arg(long, default_value = "21-25", conflicts_with = "config", value_parser = parse_numbers)]
+    problems: Numbers,
+    /// Where to write the problems.
+    #[arg(long, default_value = "aops.html")]
+    out: PathBuf,
+    /// Where to write the solutions.
+    #[arg(long, default_value = "aops_solution.html")]
+    solution_out: PathBuf,
+    /// Write one problems/solutions pair per year under this directory, as
+    /// `{challenge}/{year}/`, plus a `manifest.json`, instead of `--out` and
+    /// `--solution-out`.
+    #[arg(long, value_name = "ROOT", conflicts_with_all = ["out", "solution_out"])]
+    out_dir: Option<PathBuf>,
+    /// Re-run the scrape every time the config file changes.
+    #[arg(short, long, requires = "config")]
+    watch: bool,
+    /// Only download the configured pages into the config's `cache_dir`, for
+    /// rendering offline later.
+    #[arg(long, requires = "config", conflicts_with = "watch")]
+    warm_cache: bool,
+    /// Re-parse the rendered problems and solutions and report malformed
+    /// markup per problem.
+    #[arg(long)]
+    validate: bool,
+    /// Print a one-line-per-problem index of what was scraped.
+    #[arg(long)]
+    index: bool,
+    /// Also write `--combined-out`, with each solution folded under its
+    /// problem.
+    #[arg(long)]
+    combined: bool,
+    /// Where to write the combined problems and solutions.
+    #[arg(long, default_value = "aops_combined.html")]
+    combined_out: PathBuf,
+    /// Parse every saved page in a directory and report parser coverage,
+    /// without fetching or rendering anything.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["config", "watch"])]
+    check: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+struct Numbers(Vec<RangeInclusive<u32>>);
+
+// comma-separated numbers and `start-end` ranges
+fn parse_numbers(s: &str) -> Result<Numbers, String> {
+    let number = |n: &str| {
+        n.trim()
+            .parse::<u32>()
+            .map_err(|_| format!("`{}` is not a number", n.trim()))
+    };
+    s.split(',')
+        .map(|part| match part.split_once('-') {
+            Some((start, end)) => Ok(number(start)?..=number(end)?),
+            None => number(part).map(|n| n..=n),
+        })
+        .collect::<Result<_, _>>()
+        .map(Numbers)
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let scraper = AopsScraperBuilder::default()
-        .challenge(Challenge::Amc8)
-        .years(&[2003..=2020, 2022..=2023])
-        .problems(21..=25)
-        .build()?;
+    let args = Args::parse();
+
+    if let Some(dir) = &args.check {
+        print!("{}", aops::format_checks(&aops::check_dir(dir)?));
+        return Ok(());
+    }
 
+    if let (Some(config), true) = (&args.config, args.warm_cache) {
+        let report = load_config(config)?.warm_cache().await?;
+        if !report.years_before_contest.is_empty() {
+            eprintln!(
+                "Skipped years before the contest started: {:?}",
+                report.years_before_contest
+            );
+        }
+        eprintln!("Downloaded {} bytes", report.bytes_downloaded);
+        if !report.skipped.is_empty() {
+            eprintln!("Skipped (byte budget): {:?}", report.skipped);
+        }
+        if !report.missing.is_empty() {
+            eprintln!("Not on the wiki: {:?}", report.missing);
+        }
+        for e in &report.errors {
+            eprintln!("Failed {} problem {}: {}", e.year, e.number, e.error);
+        }
+        return Ok(());
+    }
+
+    match (&args.config, args.watch) {
+        (Some(config), true) => watch(config, &args).await,
+        (Some(config), false) => run(load_config(config)?, &args).await,
+        (None, _) => run(scraper_from_args(&args)?, &args).await,
+    }
+}
+
+async fn run(scraper: AopsScraper, args: &Args) -> Result<()> {
     let mut ret = scraper.scrape().await?;
-    let problems = ret.generate_problem()?;
-    let solutions = ret.generate_solution()?;
+    report_too_early(ret.challenge, &ret.report.years_before_contest);
+    for e in &ret.errors {
+        eprintln!("Skipped {} problem {}: {}", e.year, e.number, e.error);
+    }
+    for (url, error) in &ret.style_errors {
+        eprintln!("Failed to inline {url}: {error}");
+    }
+    if !ret.report.missing.is_empty() {
+        eprintln!("Not on the wiki: {:?}", ret.report.missing);
+    }
+    if args.index {
+        print!("{}", ret.index());
+    }
+    if args.validate {
+        for issue in ret.validate() {
+            let part = if issue.solution {
+                "solution"
+            } else {
+                "problem"
+            };
+            eprintln!(
+                "{} problem {} ({part}): {}",
+                issue.year, issue.number, issue.message
+            );
+        }
+    }
+    if let Some(root) = &args.out_dir {
+        ret.write_tree(root)
+            .with_context(|| format!("Failed to write {}", root.display()))?;
+    } else {
+        let problems = ret.generate_problem()?;
+        let solutions = ret.generate_solution()?;
 
-    fs::write("aops.html", problems)?;
-    fs::write("aops_solution.html", solutions)?;
+        fs::write(&args.out, problems)
+            .with_context(|| format!("Failed to write {}", args.out.display()))?;
+        fs::write(&args.solution_out, solutions)
+            .with_context(|| format!("Failed to write {}", args.solution_out.display()))?;
+    }
+    if args.combined {
+        fs::write(&args.combined_out, ret.generate_combined()?)
+            .with_context(|| format!("Failed to write {}", args.combined_out.display()))?;
+    }
     Ok(())
 }
+
+async fn watch(config: &Path, args: &Args) -> Result<()> {
+    let name = config.file_name().map(|n| n.to_os_string());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if event.paths.iter().any(|p| p.file_name() == name.as_deref()) {
+                let _ = tx.send(());
+            }
+        }
+    })?;
+    // editors often replace the file on save, so watch its directory instead
+    let dir = match config.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    regenerate(config, args).await;
+    while rx.recv().await.is_some() {
+        // debounce bursts of events from a single save
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        while rx.try_recv().is_ok() {}
+        regenerate(config, args).await;
+    }
+    Ok(())
+}
+
+async fn regenerate(config: &Path, args: &Args) {
+    eprintln!("Regenerating from {}", config.display());
+    let ret = match load_config(config) {
+        Ok(scraper) => run(scraper, args).await,
+        Err(e) => Err(e),
+    };
+    match (ret, &args.out_dir) {
+        (Ok(()), Some(root)) => eprintln!("Regenerated {}", root.display()),
+        (Ok(()), None) => eprintln!(
+            "Regenerated {} and {}",
+            args.out.display(),
+            args.solution_out.display()
+        ),
+        (Err(e), _) => eprintln!("Failed to regenerate: {e:#}"),
+    }
+}
+
+fn report_too_early(challenge: Challenge, years: &[u32]) {
+    if !years.is_empty() {
+        eprintln!(
+            "{challenge} started in {}, skipped {years:?}",
+            challenge.first_year()
+        );
+    }
+}
+
+fn load_config(path: &Path) -> Result<AopsScraper> {
+    let data =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("Invalid config {}", path.display()))
+}
+
+fn scraper_from_args(args: &Args) -> Result<AopsScraper> {
+    let problems = args
+        .problems
+        .0
+        .iter()
+        .cloned()
+        .flatten()
+        .collect::<Vec<_>>();
+    Ok(AopsScraperBuilder::default()
+        .challenge(args.challenge)
+        .years(args.years.0.clone())
+        .problem_list(&problems)
+        .build()?)
+}